@@ -0,0 +1,329 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Orientation filters run on the host from the raw sensor stream. The board already fuses its own
+//! sensors, so these are meant for checking that fusion or for trying other gains. Both filters
+//! integrate the gyro and use the accelerometer, and optionally the magnetometer, to correct the
+//! drift. The gyro full scale range is reported in the stream configuration response.
+
+use crate::math::Quaternion;
+use crate::rate::scale_gyro;
+use crate::serde::Vector;
+use crate::serial::storage::RawDataUpdate;
+use crate::watch::Sample;
+
+/// The default gain of the Madgwick filter, from the original paper
+pub const DEFAULT_MADGWICK_BETA: f32 = 0.1;
+
+/// Starting gains for the Mahony filter, which behave much like the Madgwick default
+pub const DEFAULT_MAHONY_KP: f32 = 1.0;
+pub const DEFAULT_MAHONY_KI: f32 = 0.0;
+
+/// A step of zero for integrating the gyro without a correction
+const NO_STEP: Quaternion = Quaternion {
+    w: 0.0,
+    x: 0.0,
+    y: 0.0,
+    z: 0.0,
+};
+
+/// The filter used to fuse the sensors.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Algorithm {
+    /// Gradient descent towards the orientation that matches the reference directions. A larger
+    /// `beta` corrects drift faster but lets more accelerometer noise through.
+    Madgwick { beta: f32 },
+    /// A complementary filter with a PI controller on the error between the measured and expected
+    /// reference directions.
+    Mahony { kp: f32, ki: f32 },
+}
+
+impl Default for Algorithm {
+    fn default() -> Self {
+        Algorithm::Madgwick {
+            beta: DEFAULT_MADGWICK_BETA,
+        }
+    }
+}
+
+/// A host side attitude and heading reference system. The orientation uses the same convention as
+/// the quaternion reported by the board, rotating vectors from the board frame into the world
+/// frame.
+#[derive(Clone, Debug)]
+pub struct Ahrs {
+    algorithm: Algorithm,
+    gyro_fsr: u16,
+    use_magnetometer: bool,
+    gyro_bias: Vector<f32>,
+    orientation: Option<Quaternion>,
+    /// The integral term of the Mahony filter, in radians/sec
+    integral: Vector<f32>,
+    last: Option<Sample<()>>,
+}
+
+impl Ahrs {
+    pub fn new(algorithm: Algorithm, gyro_fsr: u16) -> Self {
+        Self {
+            algorithm,
+            gyro_fsr,
+            use_magnetometer: false,
+            gyro_bias: Vector::zero(),
+            orientation: None,
+            integral: Vector::zero(),
+            last: None,
+        }
+    }
+
+    pub fn madgwick(gyro_fsr: u16, beta: f32) -> Self {
+        Self::new(Algorithm::Madgwick { beta }, gyro_fsr)
+    }
+
+    pub fn mahony(gyro_fsr: u16, kp: f32, ki: f32) -> Self {
+        Self::new(Algorithm::Mahony { kp, ki }, gyro_fsr)
+    }
+
+    /// Switch filters or change gains. The current orientation is kept.
+    pub fn set_algorithm(&mut self, algorithm: Algorithm) {
+        self.algorithm = algorithm;
+        self.integral = Vector::zero();
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn set_gyro_fsr(&mut self, gyro_fsr: u16) {
+        self.gyro_fsr = gyro_fsr;
+    }
+
+    /// Correct the heading with the magnetometer. This is off by default since the motors and
+    /// steel on a robot usually disturb the field more than the gyro drifts. Without it, yaw
+    /// drifts like a plain gyro.
+    pub fn set_use_magnetometer(&mut self, use_magnetometer: bool) {
+        self.use_magnetometer = use_magnetometer;
+    }
+
+    /// Subtract a known gyro bias (degrees/sec) from every reading, such as one from
+    /// [`GyroBiasEstimator`].
+    ///
+    /// [`GyroBiasEstimator`]: crate::bias::GyroBiasEstimator
+    pub fn set_gyro_bias(&mut self, bias: Vector<f32>) {
+        self.gyro_bias = bias;
+    }
+
+    /// Feed a raw data sample. The time between samples is taken from their timestamps. The first
+    /// sample only levels the filter using the accelerometer.
+    pub fn update(&mut self, sample: &Sample<RawDataUpdate>) -> Quaternion {
+        let dt = match self.last {
+            Some(last) => sample.seconds_since(&last) as f32,
+            None => 0.0,
+        };
+        self.last = Some(sample.with_value(()));
+
+        let raw = &sample.value;
+        let gyro = scale_gyro(raw.gyro, self.gyro_fsr);
+        let accel = raw.acceleration.map(f32::from);
+        let mag = if self.use_magnetometer {
+            Some(raw.magnetometer.map(f32::from))
+        } else {
+            None
+        };
+
+        self.update_rates(gyro, accel, mag, dt)
+    }
+
+    /// Advance the filter by `dt` seconds. The gyro is in degrees/sec, while the accelerometer and
+    /// magnetometer can be in any unit since only their direction is used.
+    pub fn update_rates(
+        &mut self,
+        gyro: Vector<f32>,
+        accel: Vector<f32>,
+        mag: Option<Vector<f32>>,
+        dt: f32,
+    ) -> Quaternion {
+        let q = match self.orientation {
+            Some(q) => q,
+            None => {
+                let q = Quaternion::level(accel);
+                self.orientation = Some(q);
+                return q;
+            }
+        };
+
+        if dt <= 0.0 {
+            return q;
+        }
+
+        let gyro = (gyro - self.gyro_bias).map(f32::to_radians);
+        let accel = accel.normalize();
+        let mag = mag.map(Vector::normalize).filter(|x| x.norm() > 0.0);
+
+        let q = match self.algorithm {
+            Algorithm::Madgwick { beta } => madgwick(q, gyro, accel, mag, beta, dt),
+            Algorithm::Mahony { kp, ki } => {
+                let error = mahony_error(q, accel, mag);
+
+                let mut omega = gyro;
+                if ki > 0.0 {
+                    self.integral = self.integral + error * (ki * dt);
+                    omega = omega + self.integral;
+                }
+
+                integrate(q, omega + error * kp, NO_STEP, dt)
+            }
+        };
+
+        self.orientation = Some(q);
+        q
+    }
+
+    /// The current orientation, or the identity before the first sample
+    pub fn orientation(&self) -> Quaternion {
+        self.orientation.unwrap_or_default()
+    }
+
+    /// The current orientation as (yaw, pitch, roll) in degrees. See
+    /// [`Quaternion::to_yaw_pitch_roll`].
+    pub fn yaw_pitch_roll(&self) -> (f32, f32, f32) {
+        self.orientation().to_yaw_pitch_roll()
+    }
+
+    /// Start over from the next sample.
+    pub fn reset(&mut self) {
+        self.orientation = None;
+        self.integral = Vector::zero();
+        self.last = None;
+    }
+}
+
+/// Integrate the body rate `omega` (radians/sec) and step against `step` for `dt` seconds.
+fn integrate(q: Quaternion, omega: Vector<f32>, step: Quaternion, dt: f32) -> Quaternion {
+    let rate = q * Quaternion::new(0.0, omega.x, omega.y, omega.z);
+
+    Quaternion::new(
+        q.w + (0.5 * rate.w - step.w) * dt,
+        q.x + (0.5 * rate.x - step.x) * dt,
+        q.y + (0.5 * rate.y - step.y) * dt,
+        q.z + (0.5 * rate.z - step.z) * dt,
+    )
+    .normalize()
+}
+
+/// The direction of the magnetic field in the world frame, rotated into the XZ plane so only its
+/// inclination is compared
+fn reference_field(q: Quaternion, mag: Vector<f32>) -> (f32, f32) {
+    let h = q.rotate(mag);
+    (h.x.hypot(h.y), h.z)
+}
+
+/// Add J^T f to the gradient, where each row of J is the derivative of one component of f
+fn add_gradient(gradient: &mut [f32; 4], f: [f32; 3], j: [[f32; 4]; 3]) {
+    for (row, value) in j.iter().zip(&f) {
+        for (g, d) in gradient.iter_mut().zip(row) {
+            *g += d * value;
+        }
+    }
+}
+
+fn madgwick(
+    q: Quaternion,
+    gyro: Vector<f32>,
+    accel: Vector<f32>,
+    mag: Option<Vector<f32>>,
+    beta: f32,
+    dt: f32,
+) -> Quaternion {
+    if accel.norm() == 0.0 {
+        return integrate(q, gyro, NO_STEP, dt);
+    }
+
+    let Quaternion {
+        w: q0,
+        x: q1,
+        y: q2,
+        z: q3,
+    } = q;
+    let mut gradient = [0.0; 4];
+
+    // The difference between the expected and measured gravity in the board frame
+    let expected = q.gravity();
+    add_gradient(
+        &mut gradient,
+        [
+            expected.x - accel.x,
+            expected.y - accel.y,
+            expected.z - accel.z,
+        ],
+        [
+            [-2.0 * q2, 2.0 * q3, -2.0 * q0, 2.0 * q1],
+            [2.0 * q1, 2.0 * q0, 2.0 * q3, 2.0 * q2],
+            [0.0, -4.0 * q1, -4.0 * q2, 0.0],
+        ],
+    );
+
+    if let Some(mag) = mag {
+        let (bx, bz) = reference_field(q, mag);
+        let expected = q.conjugate().rotate(Vector::new(bx, 0.0, bz));
+
+        add_gradient(
+            &mut gradient,
+            [expected.x - mag.x, expected.y - mag.y, expected.z - mag.z],
+            [
+                [
+                    -2.0 * bz * q2,
+                    2.0 * bz * q3,
+                    -4.0 * bx * q2 - 2.0 * bz * q0,
+                    -4.0 * bx * q3 + 2.0 * bz * q1,
+                ],
+                [
+                    -2.0 * bx * q3 + 2.0 * bz * q1,
+                    2.0 * bx * q2 + 2.0 * bz * q0,
+                    2.0 * bx * q1 + 2.0 * bz * q3,
+                    -2.0 * bx * q0 + 2.0 * bz * q2,
+                ],
+                [
+                    2.0 * bx * q2,
+                    2.0 * bx * q3 - 4.0 * bz * q1,
+                    2.0 * bx * q0 - 4.0 * bz * q2,
+                    2.0 * bx * q1,
+                ],
+            ],
+        );
+    }
+
+    let [s0, s1, s2, s3] = gradient;
+    let step = Quaternion::new(s0, s1, s2, s3);
+    let norm = step.norm();
+    let step = if norm > 0.0 {
+        Quaternion::new(
+            s0 * beta / norm,
+            s1 * beta / norm,
+            s2 * beta / norm,
+            s3 * beta / norm,
+        )
+    } else {
+        NO_STEP
+    };
+
+    integrate(q, gyro, step, dt)
+}
+
+/// The rotation (radians) that would bring the expected reference directions onto the measured ones
+fn mahony_error(q: Quaternion, accel: Vector<f32>, mag: Option<Vector<f32>>) -> Vector<f32> {
+    let mut error = Vector::zero();
+
+    if accel.norm() > 0.0 {
+        error = error + accel.cross(q.gravity());
+    }
+
+    if let Some(mag) = mag {
+        let (bx, bz) = reference_field(q, mag);
+        error = error + mag.cross(q.conjugate().rotate(Vector::new(bx, 0.0, bz)));
+    }
+
+    error
+}
@@ -0,0 +1,284 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Gyro bias estimation. The board calibrates its gyro once at startup, but the bias keeps changing
+//! with temperature, which shows up as yaw drift while the robot sits disabled before a match. While
+//! the robot is known to be still, anything the gyro reads is bias, so it can be averaged and
+//! removed in software.
+
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate::collision::LinearAccelSource;
+use crate::heading::YawSource;
+use crate::motion::SensorStatusSource;
+use crate::rate::AngularRate;
+use crate::serde::{SensorStatus, Vector};
+use crate::watch::{Sample, Watcher};
+
+/// Samples which report the body rates of the gyro.
+pub trait GyroSource {
+    /// The rotation rate about each axis in degrees per second
+    fn gyro(&self) -> Vector<f32>;
+}
+
+impl GyroSource for Vector<f32> {
+    fn gyro(&self) -> Vector<f32> {
+        *self
+    }
+}
+
+impl GyroSource for AngularRate {
+    fn gyro(&self) -> Vector<f32> {
+        self.body
+    }
+}
+
+/// When the robot is considered still and how the bias is averaged.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BiasConfig {
+    /// The number of acceleration samples the variance is computed over
+    pub accel_window: usize,
+    /// The largest standard deviation of the linear acceleration (G) that counts as stationary
+    pub max_accel_std_dev: f32,
+    /// The number of stationary gyro samples needed before an estimate is reported
+    pub min_samples: usize,
+    /// The number of samples the estimate is averaged over. Older samples fade out after this so
+    /// the estimate can follow changes in temperature.
+    pub max_samples: usize,
+}
+
+impl Default for BiasConfig {
+    fn default() -> Self {
+        Self {
+            accel_window: 50,
+            max_accel_std_dev: 0.005,
+            min_samples: 200,
+            max_samples: 5000,
+        }
+    }
+}
+
+struct State {
+    config: BiasConfig,
+    sensor: Option<SensorStatus>,
+    accel: VecDeque<Vector<f32>>,
+    bias: Vector<f64>,
+    samples: usize,
+    apply: bool,
+    /// The angle added to the yaw to cancel the drift so far, in degrees
+    correction: f64,
+    last_yaw: Option<Sample<()>>,
+}
+
+impl State {
+    fn new(config: BiasConfig) -> Self {
+        Self {
+            config,
+            sensor: None,
+            accel: VecDeque::with_capacity(config.accel_window),
+            bias: Vector::new(0.0, 0.0, 0.0),
+            samples: 0,
+            apply: true,
+            correction: 0.0,
+            last_yaw: None,
+        }
+    }
+
+    fn accel_variance(&self) -> Option<f32> {
+        if self.accel.len() < self.config.accel_window.max(2) {
+            return None;
+        }
+
+        let n = self.accel.len() as f32;
+        let mean = self
+            .accel
+            .iter()
+            .fold(Vector::zero(), |sum, &x| sum + x * n.recip());
+
+        Some(
+            self.accel
+                .iter()
+                .map(|&x| (x - mean).dot(x - mean))
+                .sum::<f32>()
+                / n,
+        )
+    }
+
+    fn is_stationary(&self) -> bool {
+        let moving = self
+            .sensor
+            .is_some_and(|x| x.contains(SensorStatus::MOVING));
+        let std_dev = self.config.max_accel_std_dev;
+
+        !moving
+            && self
+                .accel_variance()
+                .is_some_and(|x| x <= std_dev * std_dev)
+    }
+
+    fn bias(&self) -> Option<Vector<f32>> {
+        if self.samples < self.config.min_samples.max(1) {
+            return None;
+        }
+
+        Some(self.bias.map(|x| x as f32))
+    }
+}
+
+/// Estimates the bias of each gyro axis while the robot is stationary. The robot counts as
+/// stationary while the board does not report `MOVING` and the linear acceleration is steady. An
+/// estimator is fed by hand or attached to watchers, and is meant to be shared through an `Arc`.
+pub struct GyroBiasEstimator {
+    state: Mutex<State>,
+}
+
+impl Default for GyroBiasEstimator {
+    fn default() -> Self {
+        Self::new(BiasConfig::default())
+    }
+}
+
+impl GyroBiasEstimator {
+    pub fn new(config: BiasConfig) -> Self {
+        Self {
+            state: Mutex::new(State::new(config)),
+        }
+    }
+
+    pub fn config(&self) -> BiasConfig {
+        self.state.lock().config
+    }
+
+    /// Record the sensor flags reported by the board.
+    pub fn observe_status<S: SensorStatusSource>(&self, sample: &Sample<S>) {
+        self.state.lock().sensor = Some(sample.value.sensor_status());
+    }
+
+    /// Record the linear acceleration of the robot.
+    pub fn observe_accel<T: LinearAccelSource>(&self, sample: &Sample<T>) {
+        let mut state = self.state.lock();
+
+        while state.accel.len() >= state.config.accel_window.max(2) {
+            state.accel.pop_front();
+        }
+        state.accel.push_back(sample.value.linear_accel());
+    }
+
+    /// Record a gyro reading. It only counts towards the estimate if the robot is stationary.
+    pub fn observe_gyro<G: GyroSource>(&self, sample: &Sample<G>) {
+        let mut state = self.state.lock();
+        if !state.is_stationary() {
+            return;
+        }
+
+        // A running mean which becomes an exponential average once it covers max_samples
+        state.samples += 1;
+        let weight = 1.0 / state.samples.min(state.config.max_samples.max(1)) as f64;
+        let gyro = sample.value.gyro();
+
+        state.bias = Vector::new(
+            state.bias.x + (f64::from(gyro.x) - state.bias.x) * weight,
+            state.bias.y + (f64::from(gyro.y) - state.bias.y) * weight,
+            state.bias.z + (f64::from(gyro.z) - state.bias.z) * weight,
+        );
+    }
+
+    /// True if gyro readings are currently counted towards the estimate.
+    pub fn is_stationary(&self) -> bool {
+        self.state.lock().is_stationary()
+    }
+
+    /// The estimated bias of each axis in degrees per second, or `None` until enough stationary
+    /// samples have been seen.
+    pub fn bias(&self) -> Option<Vector<f32>> {
+        self.state.lock().bias()
+    }
+
+    /// The yaw drift caused by the bias in degrees per minute, with the same sign as yaw. The gyro
+    /// is right handed while yaw increases clockwise, so a positive Z bias makes yaw drift down.
+    /// This uses the Z axis of the board, so it assumes the board is mounted flat.
+    pub fn drift_rate(&self) -> Option<f32> {
+        self.bias().map(|x| -x.z * 60.0)
+    }
+
+    /// The number of stationary gyro samples the estimate is made from
+    pub fn sample_count(&self) -> usize {
+        self.state.lock().samples
+    }
+
+    /// Choose whether [`correct_yaw`] removes the estimated drift. This is on by default.
+    ///
+    /// [`correct_yaw`]: GyroBiasEstimator::correct_yaw
+    pub fn set_apply_correction(&self, apply: bool) {
+        self.state.lock().apply = apply;
+    }
+
+    /// Remove the drift accumulated since the first corrected sample from a yaw sample. The result
+    /// is in [-180, 180). Yaw samples need to be fed continuously for the drift to be integrated.
+    pub fn correct_yaw<T: YawSource>(&self, sample: &Sample<T>) -> f32 {
+        let mut state = self.state.lock();
+        let yaw = sample.value.yaw_degrees();
+
+        if let (Some(last), Some(bias)) = (state.last_yaw, state.bias()) {
+            let dt = sample.seconds_since(&last);
+            if dt > 0.0 {
+                state.correction += f64::from(bias.z) * dt;
+            }
+        }
+        state.last_yaw = Some(sample.with_value(()));
+
+        if !state.apply {
+            return yaw;
+        }
+
+        // Yaw drifts at -bias.z, so the integrated bias is added back
+        ((f64::from(yaw) + state.correction + 180.0).rem_euclid(360.0) - 180.0) as f32
+    }
+
+    /// The total angle added to the yaw by [`correct_yaw`] so far, in degrees. This is the
+    /// opposite of the drift.
+    ///
+    /// [`correct_yaw`]: GyroBiasEstimator::correct_yaw
+    pub fn correction(&self) -> f64 {
+        self.state.lock().correction
+    }
+
+    /// Forget the drift removed so far, for example after the yaw was zeroed.
+    pub fn reset_correction(&self) {
+        let mut state = self.state.lock();
+        state.correction = 0.0;
+        state.last_yaw = None;
+    }
+
+    /// Forget the estimate and everything observed so far.
+    pub fn reset(&self) {
+        let mut state = self.state.lock();
+        let apply = state.apply;
+        *state = State::new(state.config);
+        state.apply = apply;
+    }
+
+    /// Feed this estimator with the sensor flags read by a watcher.
+    pub fn attach_status<T: SensorStatusSource, S>(self: &Arc<Self>, watcher: &Watcher<T, S>) {
+        let estimator = self.clone();
+        watcher.on_sample(move |sample: &Sample<T>| estimator.observe_status(sample));
+    }
+
+    /// Feed this estimator with the linear acceleration read by a watcher.
+    pub fn attach_accel<T: LinearAccelSource, S>(self: &Arc<Self>, watcher: &Watcher<T, S>) {
+        let estimator = self.clone();
+        watcher.on_sample(move |sample: &Sample<T>| estimator.observe_accel(sample));
+    }
+
+    /// Feed this estimator with the gyro readings of a watcher.
+    pub fn attach_gyro<T: GyroSource, S>(self: &Arc<Self>, watcher: &Watcher<T, S>) {
+        let estimator = self.clone();
+        watcher.on_sample(move |sample: &Sample<T>| estimator.observe_gyro(sample));
+    }
+}
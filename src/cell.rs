@@ -0,0 +1,149 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A wait-free cell holding the latest value written to it. This is what lets the robot loop read
+//! watched values without waiting on a lock held by the thread polling the board.
+
+use parking_lot::Mutex;
+use std::cell::UnsafeCell;
+use std::hint::spin_loop;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of buffers values are rotated through. A write only has to wait if readers are still
+/// copying out of every buffer but the latest one.
+const BUFFERS: usize = 4;
+
+/// The low bits of `current` hold the index of the latest buffer, the rest count readers of it
+const INDEX_MASK: usize = BUFFERS - 1;
+/// One reader in the count held by `current`
+const READER: usize = BUFFERS;
+
+/// What the writer knows about each buffer. Only touched while holding the write lock.
+struct Written {
+    /// The count of readers that entered each buffer while it was the latest one. A buffer can be
+    /// reused once as many readers have left it.
+    entered: [usize; BUFFERS],
+    initialized: [bool; BUFFERS],
+}
+
+/// Holds the latest value written to it. Writes are serialized. Reads copy the value out without
+/// taking a lock and are wait-free: a reader atomically registers with the latest buffer and copies
+/// it out, and the writer never reuses a buffer until every reader has left it.
+///
+/// Any value can be stored, but reading requires `Copy` so a read can't keep the value borrowed.
+pub struct LatestCell<T> {
+    buffers: [UnsafeCell<MaybeUninit<T>>; BUFFERS],
+    /// The index of the latest buffer and the number of readers that have entered it
+    current: AtomicUsize,
+    /// The number of readers that have left each buffer, counted in units of `READER`
+    left: [AtomicUsize; BUFFERS],
+    /// The number of values written so far
+    version: AtomicUsize,
+    written: Mutex<Written>,
+}
+
+// Values are only ever moved in by a writer and copied out by readers
+unsafe impl<T: Send> Send for LatestCell<T> {}
+unsafe impl<T: Send> Sync for LatestCell<T> {}
+
+impl<T> Default for LatestCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> LatestCell<T> {
+    pub fn new() -> Self {
+        Self {
+            buffers: [
+                UnsafeCell::new(MaybeUninit::uninit()),
+                UnsafeCell::new(MaybeUninit::uninit()),
+                UnsafeCell::new(MaybeUninit::uninit()),
+                UnsafeCell::new(MaybeUninit::uninit()),
+            ],
+            current: AtomicUsize::new(0),
+            left: Default::default(),
+            version: AtomicUsize::new(0),
+            written: Mutex::new(Written {
+                entered: [0; BUFFERS],
+                initialized: [false; BUFFERS],
+            }),
+        }
+    }
+
+    /// Replace the latest value. Concurrent writers wait on each other, but there is normally only
+    /// the one polling thread.
+    pub fn store(&self, value: T) {
+        let mut written = self.written.lock();
+        let latest = self.current.load(Ordering::Relaxed) & INDEX_MASK;
+
+        // Readers only ever enter the latest buffer, so an older one that every reader has left
+        // stays free until it is published again.
+        let index = loop {
+            let free = (1..BUFFERS)
+                .map(|i| (latest + i) % BUFFERS)
+                .find(|&i| self.left[i].load(Ordering::Acquire) == written.entered[i]);
+
+            match free {
+                Some(x) => break x,
+                None => spin_loop(),
+            }
+        };
+
+        unsafe {
+            let buffer = &mut *self.buffers[index].get();
+            if written.initialized[index] {
+                ptr::drop_in_place(buffer.as_mut_ptr());
+            }
+            ptr::write(buffer, MaybeUninit::new(value));
+        }
+        written.initialized[index] = true;
+        written.entered[index] = 0;
+        self.left[index].store(0, Ordering::Relaxed);
+
+        let previous = self.current.swap(index, Ordering::AcqRel);
+        written.entered[latest] = previous & !INDEX_MASK;
+
+        self.version.fetch_add(1, Ordering::Release);
+    }
+
+    /// The number of values that have been stored. This can be used to check for new values
+    /// without reading them.
+    pub fn version(&self) -> usize {
+        self.version.load(Ordering::Acquire)
+    }
+}
+
+impl<T: Copy> LatestCell<T> {
+    /// Copy out the latest value, or `None` if nothing has been stored yet. This never waits on
+    /// the writer or retries.
+    pub fn load(&self) -> Option<T> {
+        if self.version.load(Ordering::Acquire) == 0 {
+            return None;
+        }
+
+        let index = self.current.fetch_add(READER, Ordering::Acquire) & INDEX_MASK;
+        let value = unsafe { (*self.buffers[index].get()).assume_init() };
+        self.left[index].fetch_add(READER, Ordering::Release);
+
+        Some(value)
+    }
+}
+
+impl<T> Drop for LatestCell<T> {
+    fn drop(&mut self) {
+        let initialized = self.written.get_mut().initialized;
+
+        for (buffer, initialized) in self.buffers.iter_mut().zip(&initialized) {
+            if *initialized {
+                unsafe { ptr::drop_in_place(buffer.get_mut().as_mut_ptr()) }
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Collision detection. An impact shows up as a sudden change in the horizontal acceleration of the
+//! robot, so collisions are detected by thresholding the jerk (the rate of change of acceleration)
+//! along the world X and Y axes.
+
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::register::storage::LinearAccel;
+use crate::serde::Vector;
+use crate::serial::storage::PositionUpdate;
+use crate::subscribe::{Capacity, Subscribers, Subscription};
+use crate::watch::{Sample, Watcher};
+
+/// The jerk (G/s) along an axis that counts as a collision unless configured otherwise. This is a
+/// change of 0.5G between two samples at 50Hz.
+pub const DEFAULT_JERK_THRESHOLD: f32 = 25.0;
+
+/// Samples this soon after a collision are treated as part of the same impact
+pub const DEFAULT_HOLDOFF: Duration = Duration::from_millis(100);
+
+/// Samples which report the acceleration of the robot in the world frame with gravity removed.
+pub trait LinearAccelSource {
+    /// The acceleration in G
+    fn linear_accel(&self) -> Vector<f32>;
+}
+
+impl LinearAccelSource for Vector<f32> {
+    fn linear_accel(&self) -> Vector<f32> {
+        *self
+    }
+}
+
+impl LinearAccelSource for LinearAccel {
+    fn linear_accel(&self) -> Vector<f32> {
+        self.accel
+    }
+}
+
+impl LinearAccelSource for PositionUpdate {
+    fn linear_accel(&self) -> Vector<f32> {
+        self.linear_accel
+    }
+}
+
+/// A detected impact. The time of the impact is the time of the sample it was detected in.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CollisionEvent {
+    /// The jerk along the world X and Y axes (G/s)
+    pub jerk_x: f32,
+    pub jerk_y: f32,
+    /// The magnitude of the horizontal jerk (G/s)
+    pub magnitude: f32,
+    /// The direction of the jerk in the world frame in degrees, counterclockwise from the X axis
+    /// (right) in the range -180 to 180. This is the direction the robot was pushed, so hitting a
+    /// wall while driving forward (+Y) gives a direction of about -90.
+    pub direction: f32,
+}
+
+impl CollisionEvent {
+    fn new(jerk_x: f32, jerk_y: f32) -> Self {
+        Self {
+            jerk_x,
+            jerk_y,
+            magnitude: jerk_x.hypot(jerk_y),
+            direction: jerk_y.atan2(jerk_x).to_degrees(),
+        }
+    }
+}
+
+struct State {
+    threshold_x: f32,
+    threshold_y: f32,
+    holdoff: Duration,
+    last: Option<Sample<Vector<f32>>>,
+    last_collision: Option<Sample<CollisionEvent>>,
+    /// The collision that has not been taken yet
+    pending: Option<Sample<CollisionEvent>>,
+}
+
+/// Detects collisions from timestamped acceleration samples. Events are kept in a flag to be polled
+/// by the robot loop and are also published to subscribers. It is meant to be shared through an
+/// `Arc`.
+pub struct CollisionDetector {
+    state: Mutex<State>,
+    subscribers: Subscribers<CollisionEvent>,
+}
+
+impl Default for CollisionDetector {
+    fn default() -> Self {
+        Self::new(DEFAULT_JERK_THRESHOLD, DEFAULT_JERK_THRESHOLD)
+    }
+}
+
+impl CollisionDetector {
+    /// Create a detector that reports a collision when the jerk along the X or Y axis exceeds the
+    /// given threshold (G/s).
+    pub fn new(threshold_x: f32, threshold_y: f32) -> Self {
+        Self {
+            state: Mutex::new(State {
+                threshold_x,
+                threshold_y,
+                holdoff: DEFAULT_HOLDOFF,
+                last: None,
+                last_collision: None,
+                pending: None,
+            }),
+            subscribers: Subscribers::new(),
+        }
+    }
+
+    /// Set how long after a collision further samples are treated as part of the same impact.
+    pub fn with_holdoff(self, holdoff: Duration) -> Self {
+        self.state.lock().holdoff = holdoff;
+        self
+    }
+
+    pub fn set_thresholds(&self, threshold_x: f32, threshold_y: f32) {
+        let mut state = self.state.lock();
+        state.threshold_x = threshold_x;
+        state.threshold_y = threshold_y;
+    }
+
+    /// The X and Y jerk thresholds (G/s)
+    pub fn thresholds(&self) -> (f32, f32) {
+        let state = self.state.lock();
+        (state.threshold_x, state.threshold_y)
+    }
+
+    /// Feed a new acceleration sample, returning the collision it caused if there was one. Samples
+    /// that share a timestamp with the previous one are ignored.
+    pub fn observe<T: LinearAccelSource>(
+        &self,
+        sample: &Sample<T>,
+    ) -> Option<Sample<CollisionEvent>> {
+        let sample = sample.with_value(sample.value.linear_accel());
+
+        let event = {
+            let mut state = self.state.lock();
+            let last = match state.last {
+                Some(last) => last,
+                None => {
+                    state.last = Some(sample);
+                    return None;
+                }
+            };
+
+            let dt = sample.seconds_since(&last);
+            if dt <= 0.0 {
+                return None;
+            }
+            state.last = Some(sample);
+
+            let jerk_x = (f64::from(sample.value.x - last.value.x) / dt) as f32;
+            let jerk_y = (f64::from(sample.value.y - last.value.y) / dt) as f32;
+
+            if jerk_x.abs() <= state.threshold_x && jerk_y.abs() <= state.threshold_y {
+                return None;
+            }
+
+            if let Some(previous) = state.last_collision {
+                if sample.seconds_since(&previous) < state.holdoff.as_secs_f64() {
+                    return None;
+                }
+            }
+
+            let event = sample.with_value(CollisionEvent::new(jerk_x, jerk_y));
+            state.last_collision = Some(event);
+            state.pending = Some(event);
+            event
+        };
+
+        // Subscribers are run without holding the state lock so they can query the detector
+        self.subscribers.publish(&event);
+        Some(event)
+    }
+
+    /// True if a collision has been detected since the last call to [`take_collision`].
+    ///
+    /// [`take_collision`]: CollisionDetector::take_collision
+    pub fn is_collision_detected(&self) -> bool {
+        self.state.lock().pending.is_some()
+    }
+
+    /// Take the latest collision that has not been taken yet, clearing the flag.
+    pub fn take_collision(&self) -> Option<Sample<CollisionEvent>> {
+        self.state.lock().pending.take()
+    }
+
+    /// Forget the previous sample and any pending collision. Call this after the stream of samples
+    /// was interrupted so the gap is not mistaken for an impact.
+    pub fn reset(&self) {
+        let mut state = self.state.lock();
+        state.last = None;
+        state.last_collision = None;
+        state.pending = None;
+    }
+
+    /// Receive every collision detected from now on.
+    pub fn subscribe(&self, capacity: Capacity) -> Subscription<CollisionEvent> {
+        self.subscribers.subscribe(capacity)
+    }
+
+    /// Call a function with every collision detected from now on. The function is run on the
+    /// thread feeding the detector, so it should return quickly.
+    pub fn on_collision<F: 'static + Fn(&Sample<CollisionEvent>) + Send>(&self, f: F) {
+        self.subscribers.listen(Box::new(move |event| {
+            f(event);
+            true
+        }));
+    }
+
+    /// Feed this detector with the samples read by a watcher. Use a watcher synced to the board
+    /// timestamp so the jerk is computed from the time the samples were measured.
+    pub fn attach<T: LinearAccelSource, S>(self: &Arc<Self>, watcher: &Watcher<T, S>) {
+        let detector = self.clone();
+        watcher.on_sample(move |sample: &Sample<T>| {
+            detector.observe(sample);
+        });
+    }
+}
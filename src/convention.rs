@@ -0,0 +1,277 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Coordinate conventions for the values reported by the board.
+//!
+//! The board reports everything in its own frame, with X to the right, Y forward and Z up as
+//! printed on the board. Pitch is a rotation about X and roll a rotation about Y, both right handed,
+//! so pitch is positive with the nose up and roll is positive with the right side down. Yaw and the
+//! headings are the odd ones out, since they increase clockwise when viewed from above.
+//!
+//! Other libraries usually expect one of the standard conventions instead, such as NWU for WPILib
+//! kinematics. Converting in one place avoids negating values by hand throughout robot code.
+
+use std::f32::consts::{FRAC_PI_2, PI};
+use std::io;
+
+use crate::heading::ContinuousYaw;
+use crate::math::Quaternion;
+use crate::mount::Flip;
+use crate::rate::AngularRate;
+use crate::register::storage::{LinearAccel, Orientation, RawGyro};
+use crate::serde::Vector;
+use crate::serial::storage::{DirectionalUpdate, PositionUpdate, RawDataUpdate};
+use crate::watch::{Watch, Watched};
+use crate::Request;
+
+/// The axes and angle signs that values are reported in.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CoordinateConvention {
+    /// The frame of the board: X right, Y forward, Z up with yaw positive clockwise
+    #[default]
+    Native,
+    /// X forward (north), Y left (west), Z up with yaw positive counterclockwise. This is the
+    /// convention used by WPILib.
+    Nwu,
+    /// X right (east), Y forward (north), Z up with yaw positive counterclockwise
+    Enu,
+    /// X forward (north), Y right (east), Z down with yaw positive clockwise, as used in aerospace
+    Ned,
+}
+
+impl CoordinateConvention {
+    /// True if yaw and headings increase clockwise when viewed from above.
+    pub fn is_clockwise(&self) -> bool {
+        match self {
+            CoordinateConvention::Native | CoordinateConvention::Ned => true,
+            CoordinateConvention::Nwu | CoordinateConvention::Enu => false,
+        }
+    }
+
+    /// Convert a vector, such as an acceleration or a body rotation rate, from the frame of the
+    /// board. The same mapping applies to vectors in the world frame.
+    pub fn vector<T: Flip>(&self, v: Vector<T>) -> Vector<T> {
+        let Vector { x, y, z } = v;
+
+        match self {
+            CoordinateConvention::Native | CoordinateConvention::Enu => v,
+            CoordinateConvention::Nwu => Vector::new(y, x.flip(), z),
+            CoordinateConvention::Ned => Vector::new(y, x, z.flip()),
+        }
+    }
+
+    /// The rotation that takes vectors from the frame of the board into this convention
+    pub fn rotation(&self) -> Quaternion {
+        match self {
+            CoordinateConvention::Native | CoordinateConvention::Enu => Quaternion::IDENTITY,
+            CoordinateConvention::Nwu => {
+                Quaternion::from_axis_angle(Vector::new(0.0, 0.0, 1.0), -FRAC_PI_2)
+            }
+            CoordinateConvention::Ned => {
+                Quaternion::from_axis_angle(Vector::new(1.0, 1.0, 0.0), PI)
+            }
+        }
+    }
+
+    /// Convert an orientation which rotates vectors from the board frame into the world frame, so
+    /// that it does the same with both frames in this convention.
+    pub fn orientation(&self, q: Quaternion) -> Quaternion {
+        let rotation = self.rotation();
+        (rotation * q * rotation.conjugate()).normalize()
+    }
+
+    /// Convert a yaw in [-180, 180). The result stays in the same range.
+    pub fn yaw(&self, yaw: f32) -> f32 {
+        if self.is_clockwise() {
+            return yaw;
+        }
+
+        (180.0 - yaw).rem_euclid(360.0) - 180.0
+    }
+
+    /// Convert a rate or unwrapped angle about the vertical axis.
+    pub fn yaw_rate(&self, rate: f32) -> f32 {
+        if self.is_clockwise() {
+            rate
+        } else {
+            -rate
+        }
+    }
+
+    /// Convert a heading in [0, 360). The result stays in the same range.
+    pub fn heading(&self, heading: f32) -> f32 {
+        if self.is_clockwise() {
+            return heading;
+        }
+
+        (360.0 - heading).rem_euclid(360.0)
+    }
+
+    /// Convert a pitch in degrees. Only NWU measures pitch about an axis pointing left, which makes
+    /// it positive with the nose down.
+    pub fn pitch(&self, pitch: f32) -> f32 {
+        match self {
+            CoordinateConvention::Nwu => -pitch,
+            _ => pitch,
+        }
+    }
+
+    /// Convert a roll in degrees. Every convention has roll positive with the right side down.
+    pub fn roll(&self, roll: f32) -> f32 {
+        roll
+    }
+}
+
+/// Values which can be converted from the frame of the board into another convention.
+pub trait Convert {
+    fn convert(self, convention: CoordinateConvention) -> Self;
+}
+
+impl<T: Flip> Convert for Vector<T> {
+    fn convert(self, convention: CoordinateConvention) -> Self {
+        convention.vector(self)
+    }
+}
+
+impl Convert for Quaternion {
+    fn convert(self, convention: CoordinateConvention) -> Self {
+        convention.orientation(self)
+    }
+}
+
+impl Convert for ContinuousYaw {
+    fn convert(self, convention: CoordinateConvention) -> Self {
+        if convention.is_clockwise() {
+            return self;
+        }
+
+        ContinuousYaw::from_angle(-self.angle)
+    }
+}
+
+impl Convert for RawGyro {
+    fn convert(self, convention: CoordinateConvention) -> Self {
+        Self {
+            gyro: convention.vector(self.gyro),
+        }
+    }
+}
+
+impl Convert for RawDataUpdate {
+    fn convert(self, convention: CoordinateConvention) -> Self {
+        Self {
+            gyro: convention.vector(self.gyro),
+            acceleration: convention.vector(self.acceleration),
+            magnetometer: convention.vector(self.magnetometer),
+            temperature: self.temperature,
+        }
+    }
+}
+
+impl Convert for AngularRate {
+    fn convert(self, convention: CoordinateConvention) -> Self {
+        Self {
+            body: convention.vector(self.body),
+            yaw_rate: convention.yaw_rate(self.yaw_rate),
+        }
+    }
+}
+
+impl Convert for LinearAccel {
+    fn convert(self, convention: CoordinateConvention) -> Self {
+        Self {
+            accel: convention.vector(self.accel),
+        }
+    }
+}
+
+impl Convert for Orientation {
+    fn convert(self, convention: CoordinateConvention) -> Self {
+        Self {
+            yaw: convention.yaw(self.yaw),
+            pitch: convention.pitch(self.pitch),
+            roll: convention.roll(self.roll),
+            compass_heading: convention.heading(self.compass_heading),
+            fused_heading: convention.heading(self.fused_heading),
+            ..self
+        }
+    }
+}
+
+impl Convert for DirectionalUpdate {
+    fn convert(self, convention: CoordinateConvention) -> Self {
+        Self {
+            yaw: convention.yaw(self.yaw),
+            pitch: convention.pitch(self.pitch),
+            roll: convention.roll(self.roll),
+            compass_heading: convention.heading(self.compass_heading),
+        }
+    }
+}
+
+impl Convert for PositionUpdate {
+    fn convert(self, convention: CoordinateConvention) -> Self {
+        Self {
+            yaw: convention.yaw(self.yaw),
+            pitch: convention.pitch(self.pitch),
+            roll: convention.roll(self.roll),
+            compass_heading: convention.heading(self.compass_heading),
+            fused_heading: convention.heading(self.fused_heading),
+            linear_accel: convention.vector(self.linear_accel),
+            linear_velocity: convention.vector(self.linear_velocity),
+            displacement: convention.vector(self.displacement),
+            quaternion: convention.orientation(self.quaternion),
+            ..self
+        }
+    }
+}
+
+/// Converts every value read from a provider into a coordinate convention.
+pub struct Converted<S> {
+    inner: S,
+    convention: CoordinateConvention,
+}
+
+impl<S> Converted<S> {
+    pub fn new(inner: S, convention: CoordinateConvention) -> Self {
+        Self { inner, convention }
+    }
+
+    pub fn convention(&self) -> CoordinateConvention {
+        self.convention
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Request<T>, T: Convert> Request<T> for Converted<S> {
+    fn read(&mut self) -> io::Result<T> {
+        Ok(self.inner.read()?.convert(self.convention))
+    }
+
+    fn recover(&mut self) -> io::Result<()> {
+        self.inner.recover()
+    }
+
+    fn update_rate(&mut self) -> Option<u32> {
+        self.inner.update_rate()
+    }
+}
+
+impl<S, T> Watch<T> for Converted<S>
+where
+    S: 'static + Request<T> + Send,
+    T: 'static + Convert + Send,
+{
+    type Provider = Self;
+
+    fn watch(self) -> Watched<T, Self::Provider> {
+        Watched::new(self)
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Pose estimation that fuses odometry with delayed absolute measurements such as vision. A camera
+//! pipeline reports where the robot was when the frame was captured, which is usually 50 to 150ms
+//! before the result arrives. The estimator keeps a history of its own poses so the measurement can
+//! be compared against where it thought the robot was at that moment, rather than where it is now.
+
+use std::time::{Duration, Instant};
+
+use crate::heading::ContinuousYaw;
+use crate::history::History;
+use crate::odometry::{Kinematics, Odometry, Pose};
+use crate::watch::Sample;
+
+/// The number of odometry updates kept for rewinding
+pub const HISTORY_CAPACITY: usize = 512;
+
+/// Measurements captured longer ago than this are ignored
+pub const HISTORY_MAX_AGE: Duration = Duration::from_millis(1500);
+
+/// The expected error of a pose along each axis, as a standard deviation. Distances are in the same
+/// unit as the odometry and the heading is in degrees.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StdDevs {
+    pub x: f64,
+    pub y: f64,
+    pub heading: f64,
+}
+
+impl StdDevs {
+    pub fn new(x: f64, y: f64, heading: f64) -> Self {
+        Self { x, y, heading }
+    }
+}
+
+/// How much of the difference between a measurement and the estimate is applied. This is the
+/// steady state gain of a Kalman filter with the given state and measurement standard deviations.
+fn gain(state: f64, measurement: f64) -> f64 {
+    let (q, r) = (state * state, measurement * measurement);
+
+    if q + r == 0.0 {
+        return 0.0;
+    }
+
+    q / (q + r)
+}
+
+/// Tracks the pose of the robot from odometry and corrects it with delayed measurements of the
+/// field pose.
+pub struct PoseEstimator<K> {
+    odometry: Odometry<K>,
+    history: History<Pose>,
+    yaw: ContinuousYaw,
+    state_std_devs: StdDevs,
+    vision_std_devs: StdDevs,
+}
+
+impl<K: Kinematics> PoseEstimator<K> {
+    /// Start tracking from `pose`, with `yaw` being the current navX angle.
+    pub fn new(kinematics: K, yaw: ContinuousYaw, pose: Pose) -> Self {
+        Self {
+            odometry: Odometry::new(kinematics, yaw, pose),
+            history: History::new(HISTORY_CAPACITY).with_max_age(HISTORY_MAX_AGE),
+            yaw,
+            state_std_devs: StdDevs::new(0.1, 0.1, 1.0),
+            vision_std_devs: StdDevs::new(0.9, 0.9, 30.0),
+        }
+    }
+
+    /// Set how much the odometry is trusted. Smaller values make measurements less influential.
+    pub fn set_state_std_devs(&mut self, std_devs: StdDevs) {
+        self.state_std_devs = std_devs;
+    }
+
+    /// Set how much measurements are trusted when no standard deviations are given with them.
+    pub fn set_vision_std_devs(&mut self, std_devs: StdDevs) {
+        self.vision_std_devs = std_devs;
+    }
+
+    /// Move the robot to `pose` and forget the history.
+    pub fn reset(&mut self, yaw: ContinuousYaw, pose: Pose) {
+        self.odometry.reset(yaw, pose);
+        self.history.clear();
+        self.yaw = yaw;
+    }
+
+    /// Add the distances travelled by the wheels since the last update, with `time` being when they
+    /// were read.
+    pub fn update_at(&mut self, time: Instant, yaw: ContinuousYaw, deltas: &K::Deltas) -> Pose {
+        let pose = self.odometry.update(yaw, deltas);
+        self.yaw = yaw;
+        self.history.push(Sample {
+            host_time: time,
+            board_timestamp: None,
+            value: pose,
+        });
+
+        pose
+    }
+
+    /// Add the distances travelled by the wheels since the last update, read just now.
+    pub fn update(&mut self, yaw: ContinuousYaw, deltas: &K::Deltas) -> Pose {
+        self.update_at(Instant::now(), yaw, deltas)
+    }
+
+    /// Fuse a pose measured at `capture_time` using the default vision standard deviations.
+    /// Returns false if the measurement is older than the history and was ignored.
+    pub fn add_vision_measurement(&mut self, pose: Pose, capture_time: Instant) -> bool {
+        let std_devs = self.vision_std_devs;
+        self.add_vision_measurement_with_std_devs(pose, capture_time, std_devs)
+    }
+
+    /// Fuse a pose measured at `capture_time`. The estimate is rewound to the capture time, moved
+    /// towards the measurement in proportion to how much each is trusted, and every odometry
+    /// update since then is replayed on top of the corrected pose.
+    pub fn add_vision_measurement_with_std_devs(
+        &mut self,
+        pose: Pose,
+        capture_time: Instant,
+        std_devs: StdDevs,
+    ) -> bool {
+        let past = match self.history.at(capture_time) {
+            Some(x) => x,
+            None => return false,
+        };
+
+        // The measured heading is taken to be the closest equivalent angle to the estimate
+        let heading_error = (pose.heading - past.heading + 180.0).rem_euclid(360.0) - 180.0;
+        let state = self.state_std_devs;
+        let corrected = Pose {
+            x: past.x + gain(state.x, std_devs.x) * (pose.x - past.x),
+            y: past.y + gain(state.y, std_devs.y) * (pose.y - past.y),
+            heading: past.heading + gain(state.heading, std_devs.heading) * heading_error,
+        };
+
+        // The motion since the capture is unchanged, so replaying it only moves it along with
+        // the corrected starting point
+        for sample in self.history.iter_mut() {
+            if sample.host_time >= capture_time {
+                sample.value = corrected.transform(&sample.value.relative_to(&past));
+            }
+        }
+
+        let latest = corrected.transform(&self.odometry.pose().relative_to(&past));
+        self.odometry.reset(self.yaw, latest);
+
+        true
+    }
+
+    pub fn pose(&self) -> Pose {
+        self.odometry.pose()
+    }
+
+    /// The estimated pose at some point in the recent past
+    pub fn pose_at(&self, time: Instant) -> Option<Pose> {
+        self.history.at(time)
+    }
+
+    pub fn odometry(&self) -> &Odometry<K> {
+        &self.odometry
+    }
+
+    pub fn kinematics_mut(&mut self) -> &mut K {
+        self.odometry.kinematics_mut()
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Field oriented driving. Joystick input is given relative to the driver, so it has to be rotated
+//! by the heading of the robot before being sent to the drivetrain. Drivers on the red alliance face
+//! the other way down the field, so their forward is 180 degrees from the blue one.
+//!
+//! Headings follow the WPILib convention of degrees counterclockwise, with zero facing away from the
+//! blue alliance wall. The field zero is kept here rather than on the board, so zeroing the yaw for
+//! display or autonomous does not move it. It should be fed the yaw before any software offsets are
+//! applied, and captured again if the yaw is reset on the board.
+
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+use crate::heading::YawSource;
+use crate::watch::{Sample, Watcher};
+
+/// Wrap an angle in degrees into [-180, 180).
+fn wrap(angle: f64) -> f64 {
+    (angle + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// The side of the field the drivers stand on.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Alliance {
+    /// Drivers face along the field heading of zero
+    #[default]
+    Blue,
+    /// Drivers face along the field heading of 180
+    Red,
+}
+
+impl Alliance {
+    /// The field heading the drivers of this alliance face
+    pub fn forward(&self) -> f64 {
+        match self {
+            Alliance::Blue => 0.0,
+            Alliance::Red => 180.0,
+        }
+    }
+
+    /// Turn a field heading into one relative to the drivers of this alliance, or back again.
+    pub fn flip_heading(&self, heading: f64) -> f64 {
+        wrap(heading - self.forward())
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// The latest yaw reported by the board
+    yaw: Option<f32>,
+    /// The board yaw at which the robot faces along the field heading of zero
+    zero: f64,
+    alliance: Alliance,
+}
+
+impl State {
+    fn field_heading(&self) -> Option<f64> {
+        self.yaw.map(|yaw| wrap(self.zero - f64::from(yaw)))
+    }
+}
+
+/// Tracks the heading of the robot on the field from the board yaw. An orientation is fed by hand
+/// or attached to a watcher, and is meant to be shared through an `Arc`.
+#[derive(Default)]
+pub struct FieldOrientation {
+    state: Mutex<State>,
+}
+
+impl FieldOrientation {
+    pub fn new(alliance: Alliance) -> Self {
+        Self {
+            state: Mutex::new(State {
+                alliance,
+                ..State::default()
+            }),
+        }
+    }
+
+    /// Record the yaw reported by the board.
+    pub fn observe<T: YawSource>(&self, sample: &Sample<T>) {
+        self.state.lock().yaw = Some(sample.value.yaw_degrees());
+    }
+
+    pub fn set_alliance(&self, alliance: Alliance) {
+        self.state.lock().alliance = alliance;
+    }
+
+    pub fn alliance(&self) -> Alliance {
+        self.state.lock().alliance
+    }
+
+    /// Capture the field zero with the robot facing directly away from its own drivers. Does
+    /// nothing before the first yaw has been observed.
+    pub fn zero(&self) {
+        let mut state = self.state.lock();
+        let forward = state.alliance.forward();
+
+        if let Some(yaw) = state.yaw {
+            state.zero = f64::from(yaw) + forward;
+        }
+    }
+
+    /// Make the current field heading read as `heading`, for example from the known starting
+    /// position in autonomous. Does nothing before the first yaw has been observed.
+    pub fn set_field_heading(&self, heading: f64) {
+        let mut state = self.state.lock();
+
+        if let Some(yaw) = state.yaw {
+            state.zero = f64::from(yaw) + heading;
+        }
+    }
+
+    /// The heading of the robot on the field in [-180, 180), or `None` before the first yaw has
+    /// been observed
+    pub fn field_heading(&self) -> Option<f64> {
+        self.state.lock().field_heading()
+    }
+
+    /// The heading of the robot relative to its drivers in [-180, 180). Zero is facing directly
+    /// away from them on either alliance.
+    pub fn driver_heading(&self) -> Option<f64> {
+        let state = self.state.lock();
+        state
+            .field_heading()
+            .map(|heading| state.alliance.flip_heading(heading))
+    }
+
+    /// Turn a velocity relative to the drivers into one relative to the robot. `vx` is away from
+    /// the drivers and `vy` is to their left, so both joystick axes usually need to be negated. The
+    /// velocity is returned unchanged before the first yaw has been observed.
+    pub fn field_relative(&self, vx: f64, vy: f64) -> (f64, f64) {
+        let heading = self.driver_heading().unwrap_or(0.0);
+        let (sin, cos) = heading.to_radians().sin_cos();
+
+        (vx * cos + vy * sin, -vx * sin + vy * cos)
+    }
+
+    /// Feed this orientation with the yaw read by a watcher.
+    pub fn attach<T: YawSource, S>(self: &Arc<Self>, watcher: &Watcher<T, S>) {
+        let orientation = self.clone();
+        watcher.on_sample(move |sample: &Sample<T>| orientation.observe(sample));
+    }
+}
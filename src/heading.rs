@@ -0,0 +1,420 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Heading tracking on top of the yaw reported by the board.
+//!
+//! The offsets here are applied in software to every sample as it is read, so they take effect
+//! immediately. This is separate from the board side `ControlReset::YAW`, which zeroes the yaw on
+//! the board itself and only shows up a cycle or more later. The two can be combined, in which
+//! case the software offset is applied on top of whatever the board reports.
+
+use parking_lot::Mutex;
+use std::io;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::math::Quaternion;
+use crate::register::storage::Orientation;
+use crate::serial::storage::{DirectionalUpdate, PositionUpdate};
+use crate::watch::{Watch, Watched};
+use crate::Request;
+
+/// Any sample that carries a yaw angle in degrees.
+pub trait YawSource {
+    /// The yaw of this sample in degrees. The board reports yaw in [-180, 180).
+    fn yaw_degrees(&self) -> f32;
+}
+
+impl YawSource for f32 {
+    fn yaw_degrees(&self) -> f32 {
+        *self
+    }
+}
+
+impl YawSource for Orientation {
+    fn yaw_degrees(&self) -> f32 {
+        self.yaw
+    }
+}
+
+impl YawSource for DirectionalUpdate {
+    fn yaw_degrees(&self) -> f32 {
+        self.yaw
+    }
+}
+
+impl YawSource for PositionUpdate {
+    fn yaw_degrees(&self) -> f32 {
+        self.yaw
+    }
+}
+
+/// The yaw in the convention of the board, so it matches the yaw reported alongside the quaternion
+/// even when the board is tilted.
+impl YawSource for Quaternion {
+    fn yaw_degrees(&self) -> f32 {
+        self.to_board_angles().0
+    }
+}
+
+/// Turns the wrapped yaw from the board into a continuous angle by counting full turns. A jump of
+/// more than half a turn between two consecutive samples is assumed to be a wraparound, so samples
+/// need to be fed faster than the robot can rotate 180 degrees.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct YawAccumulator {
+    last: Option<f32>,
+    turns: i64,
+}
+
+impl YawAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a new yaw sample in degrees and get the resulting continuous angle.
+    pub fn update(&mut self, yaw: f32) -> ContinuousYaw {
+        if let Some(last) = self.last {
+            let delta = yaw - last;
+
+            if delta < -180.0 {
+                self.turns += 1;
+            } else if delta > 180.0 {
+                self.turns -= 1;
+            }
+        }
+
+        self.last = Some(yaw);
+        self.get()
+    }
+
+    /// The most recent state of the accumulator. Before the first sample this is all zeros.
+    pub fn get(&self) -> ContinuousYaw {
+        let yaw = self.last.unwrap_or(0.0);
+
+        ContinuousYaw {
+            yaw,
+            angle: self.turns as f64 * 360.0 + f64::from(yaw),
+            turns: self.turns,
+        }
+    }
+
+    /// Forget all counted turns. The next sample becomes the new starting point.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// A yaw angle along with its unwrapped equivalent.
+#[derive(Copy, Clone, Debug, Default, PartialOrd, PartialEq)]
+pub struct ContinuousYaw {
+    /// The yaw in [-180, 180)
+    pub yaw: f32,
+    /// The continuous angle which keeps counting past ±180 in the same way as WPILib's `getAngle`
+    pub angle: f64,
+    /// The number of full turns that have been completed. Positive in the direction of positive yaw.
+    pub turns: i64,
+}
+
+impl ContinuousYaw {
+    /// Split a continuous angle into its wrapped yaw and number of turns.
+    pub fn from_angle(angle: f64) -> Self {
+        let turns = ((angle + 180.0) / 360.0).floor();
+
+        Self {
+            yaw: (angle - turns * 360.0) as f32,
+            angle,
+            turns: turns as i64,
+        }
+    }
+}
+
+/// Wrap an angle in degrees into [min, min + 360)
+fn wrap_degrees(angle: f64, min: f64) -> f64 {
+    (angle - min).rem_euclid(360.0) + min
+}
+
+/// A software adjustment of the yaw reported by the board. The adjusted angle is
+/// `adjustment + (raw - zero)`, with the sign of `raw - zero` flipped when inverted. Angles are in
+/// degrees.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct YawOffset {
+    zero: f64,
+    adjustment: f64,
+    inverted: bool,
+}
+
+impl YawOffset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make the raw angle currently reported by the board read as `angle`. For example, use 180
+    /// when the robot starts facing backwards.
+    pub fn set_yaw(&mut self, raw: f64, angle: f64) {
+        let relative = angle - self.adjustment;
+        self.zero = raw - if self.inverted { -relative } else { relative };
+    }
+
+    /// Add a constant to every adjusted angle, in the same way as WPILib's `setAngleAdjustment`.
+    pub fn set_adjustment(&mut self, adjustment: f64) {
+        self.adjustment = adjustment;
+    }
+
+    pub fn adjustment(&self) -> f64 {
+        self.adjustment
+    }
+
+    /// Flip the sign convention so that positive angles are counter-clockwise instead of the
+    /// board's clockwise.
+    pub fn set_inverted(&mut self, inverted: bool) {
+        self.inverted = inverted;
+    }
+
+    pub fn is_inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// Adjust an unwrapped angle.
+    pub fn angle(&self, raw: f64) -> f64 {
+        let relative = raw - self.zero;
+        self.adjustment + if self.inverted { -relative } else { relative }
+    }
+
+    /// Adjust a yaw and wrap it into [-180, 180).
+    pub fn yaw(&self, raw: f32) -> f32 {
+        wrap_degrees(self.angle(f64::from(raw)), -180.0) as f32
+    }
+
+    /// Adjust a heading and wrap it into [0, 360).
+    pub fn heading(&self, raw: f32) -> f32 {
+        wrap_degrees(self.angle(f64::from(raw)), 0.0) as f32
+    }
+
+    /// Adjust every yaw or heading in a sample.
+    pub fn apply<T: AdjustYaw>(&self, sample: T) -> T {
+        sample.adjust_yaw(self)
+    }
+}
+
+/// Samples whose yaw can be adjusted by a [`YawOffset`].
+pub trait AdjustYaw: Sized {
+    /// The raw angle used as the reference by [`YawOffset::set_yaw`]
+    fn raw_angle(&self) -> f64;
+
+    fn adjust_yaw(self, offset: &YawOffset) -> Self;
+}
+
+impl AdjustYaw for f32 {
+    fn raw_angle(&self) -> f64 {
+        f64::from(*self)
+    }
+
+    fn adjust_yaw(self, offset: &YawOffset) -> Self {
+        offset.yaw(self)
+    }
+}
+
+impl AdjustYaw for ContinuousYaw {
+    fn raw_angle(&self) -> f64 {
+        self.angle
+    }
+
+    fn adjust_yaw(self, offset: &YawOffset) -> Self {
+        ContinuousYaw::from_angle(offset.angle(self.angle))
+    }
+}
+
+impl AdjustYaw for Quaternion {
+    fn raw_angle(&self) -> f64 {
+        f64::from(self.yaw_degrees())
+    }
+
+    /// Only the yaw of the rotation is adjusted. Pitch and roll are left as they are.
+    fn adjust_yaw(self, offset: &YawOffset) -> Self {
+        let (yaw, pitch, roll) = self.to_board_angles();
+        Quaternion::from_board_angles(offset.yaw(yaw), pitch, roll)
+    }
+}
+
+impl AdjustYaw for Orientation {
+    fn raw_angle(&self) -> f64 {
+        f64::from(self.yaw)
+    }
+
+    /// The compass heading is left as it is since it is measured relative to magnetic north.
+    fn adjust_yaw(self, offset: &YawOffset) -> Self {
+        Orientation {
+            yaw: offset.yaw(self.yaw),
+            fused_heading: offset.heading(self.fused_heading),
+            ..self
+        }
+    }
+}
+
+impl AdjustYaw for DirectionalUpdate {
+    fn raw_angle(&self) -> f64 {
+        f64::from(self.yaw)
+    }
+
+    fn adjust_yaw(self, offset: &YawOffset) -> Self {
+        DirectionalUpdate {
+            yaw: offset.yaw(self.yaw),
+            ..self
+        }
+    }
+}
+
+impl AdjustYaw for PositionUpdate {
+    fn raw_angle(&self) -> f64 {
+        f64::from(self.yaw)
+    }
+
+    fn adjust_yaw(self, offset: &YawOffset) -> Self {
+        PositionUpdate {
+            yaw: offset.yaw(self.yaw),
+            fused_heading: offset.heading(self.fused_heading),
+            quaternion: self.quaternion.adjust_yaw(offset),
+            ..self
+        }
+    }
+}
+
+struct SharedState {
+    offset: YawOffset,
+    last_raw: Option<f64>,
+}
+
+/// A [`YawOffset`] shared between the thread reading samples and the rest of the robot code. It
+/// remembers the raw angle of the last sample it adjusted, so the yaw can be set at any time
+/// without knowing the raw angle. A single shared offset should only be used with one stream of
+/// samples.
+#[derive(Clone)]
+pub struct SharedYawOffset {
+    state: Arc<Mutex<SharedState>>,
+}
+
+impl Default for SharedYawOffset {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SharedYawOffset {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SharedState {
+                offset: YawOffset::new(),
+                last_raw: None,
+            })),
+        }
+    }
+
+    /// Make the current heading read as `angle` starting with the next sample. Before the first
+    /// sample, the raw angle is assumed to be zero.
+    pub fn set_yaw(&self, angle: f64) {
+        let mut state = self.state.lock();
+        let raw = state.last_raw.unwrap_or(0.0);
+        state.offset.set_yaw(raw, angle);
+    }
+
+    /// Make the current heading read as zero.
+    pub fn zero(&self) {
+        self.set_yaw(0.0)
+    }
+
+    /// See [`YawOffset::set_adjustment`]
+    pub fn set_adjustment(&self, adjustment: f64) {
+        self.state.lock().offset.set_adjustment(adjustment);
+    }
+
+    /// See [`YawOffset::set_inverted`]
+    pub fn set_inverted(&self, inverted: bool) {
+        self.state.lock().offset.set_inverted(inverted);
+    }
+
+    /// The offset currently being applied
+    pub fn get(&self) -> YawOffset {
+        self.state.lock().offset
+    }
+
+    /// Adjust a sample and remember its raw angle.
+    pub fn apply<T: AdjustYaw>(&self, sample: T) -> T {
+        let mut state = self.state.lock();
+        state.last_raw = Some(sample.raw_angle());
+        state.offset.apply(sample)
+    }
+}
+
+/// A provider that wraps another provider of yaw samples and feeds every sample it reads into a
+/// [`YawAccumulator`]. Wrap a `RegisterIO` or any other `Request` with it and watch the result to
+/// keep the continuous angle up to date in the background. The software [`offset`] is applied to
+/// every sample after accumulating.
+///
+/// [`offset`]: YawTracker::offset
+pub struct YawTracker<S, T> {
+    inner: S,
+    accumulator: YawAccumulator,
+    offset: SharedYawOffset,
+    _sample: PhantomData<fn() -> T>,
+}
+
+impl<S: Request<T>, T: YawSource> YawTracker<S, T> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            accumulator: YawAccumulator::new(),
+            offset: SharedYawOffset::new(),
+            _sample: PhantomData,
+        }
+    }
+
+    /// A handle to the offset applied to every sample. This stays usable after the tracker has
+    /// been handed off to a watcher.
+    pub fn offset(&self) -> SharedYawOffset {
+        self.offset.clone()
+    }
+
+    pub fn accumulator(&self) -> &YawAccumulator {
+        &self.accumulator
+    }
+
+    pub fn accumulator_mut(&mut self) -> &mut YawAccumulator {
+        &mut self.accumulator
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Request<T>, T: YawSource> Request<ContinuousYaw> for YawTracker<S, T> {
+    fn read(&mut self) -> io::Result<ContinuousYaw> {
+        let sample = self.inner.read()?;
+        let raw = self.accumulator.update(sample.yaw_degrees());
+        Ok(self.offset.apply(raw))
+    }
+
+    fn recover(&mut self) -> io::Result<()> {
+        self.inner.recover()
+    }
+
+    fn update_rate(&mut self) -> Option<u32> {
+        self.inner.update_rate()
+    }
+}
+
+impl<S, T> Watch<ContinuousYaw> for YawTracker<S, T>
+where
+    S: 'static + Request<T> + Send,
+    T: 'static + YawSource,
+{
+    type Provider = Self;
+
+    fn watch(self) -> Watched<ContinuousYaw, Self::Provider> {
+        Watched::new(self)
+    }
+}
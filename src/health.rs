@@ -0,0 +1,271 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Connection health tracking. This is the equivalent of `isConnected()` and `isCalibrating()` in
+//! the official navX libraries.
+
+use parking_lot::Mutex;
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::register::storage;
+use crate::serde::{CalibrationStatus, OperationStatus};
+use crate::serial;
+use crate::watch::{Sample, Watcher};
+
+/// Data older than this is considered stale unless configured otherwise
+pub const DEFAULT_STALE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The number of consecutive read errors before a board is considered disconnected
+pub const DEFAULT_DISCONNECT_ERRORS: usize = 5;
+
+/// The IMU calibration state is stored in the lowest two bits of the calibration status
+const IMU_CAL_MASK: u8 = 0b0000_0011;
+
+/// The state of the connection to a board.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Health {
+    /// No data has been received yet
+    Connecting,
+    /// The board is starting up or running its self test
+    Initializing,
+    /// The board is calibrating its IMU. Yaw will drift until this completes.
+    Calibrating,
+    /// Fresh data is arriving and the board is operating normally
+    Ready,
+    /// Data is still arriving, but reads are failing or the board is reporting an error
+    Degraded,
+    /// No fresh data has been received within the staleness timeout
+    Disconnected,
+}
+
+/// Samples which report the operating state of the board.
+pub trait StatusSource {
+    fn operation_status(&self) -> OperationStatus;
+    fn calibration_status(&self) -> CalibrationStatus;
+}
+
+impl StatusSource for storage::Status {
+    fn operation_status(&self) -> OperationStatus {
+        self.operation_status
+    }
+
+    fn calibration_status(&self) -> CalibrationStatus {
+        self.calibration_status
+    }
+}
+
+impl StatusSource for serial::storage::Status {
+    fn operation_status(&self) -> OperationStatus {
+        self.operation
+    }
+
+    fn calibration_status(&self) -> CalibrationStatus {
+        self.calibration
+    }
+}
+
+struct State {
+    health: Health,
+    stale_timeout: Duration,
+    disconnect_errors: usize,
+    last_fresh: Option<Instant>,
+    last_timestamp: Option<u32>,
+    status: Option<(OperationStatus, CalibrationStatus)>,
+    consecutive_errors: usize,
+}
+
+impl State {
+    fn is_fresh(&self, now: Instant) -> bool {
+        match self.last_fresh {
+            Some(time) => now.saturating_duration_since(time) <= self.stale_timeout,
+            None => false,
+        }
+    }
+
+    fn evaluate(&self, now: Instant) -> Health {
+        if self.last_fresh.is_none() {
+            return Health::Connecting;
+        }
+
+        if !self.is_fresh(now) || self.consecutive_errors >= self.disconnect_errors {
+            return Health::Disconnected;
+        }
+
+        if self.consecutive_errors > 0 {
+            return Health::Degraded;
+        }
+
+        match self.status {
+            Some((OperationStatus::Error, _)) => Health::Degraded,
+            Some((OperationStatus::Initializing, _))
+            | Some((OperationStatus::RunningSelfTest, _)) => Health::Initializing,
+            Some((OperationStatus::Calibrating, _)) => Health::Calibrating,
+            Some((_, cal))
+                if cal.bits() & IMU_CAL_MASK != CalibrationStatus::IMU_COMPLETE.bits() =>
+            {
+                Health::Calibrating
+            }
+            _ => Health::Ready,
+        }
+    }
+}
+
+type Callback = Box<dyn Fn(Health, Health) + Send>;
+
+/// Tracks the health of a single board. A monitor can be fed by hand or attached to watchers so it
+/// is updated as new samples and errors come in. It is meant to be shared through an `Arc`.
+///
+/// Nothing runs in the background to notice data going stale. If the watcher feeding the monitor
+/// stops or hangs, the monitor only moves to [`Health::Disconnected`] the next time it is queried
+/// or [`update`] is called, so poll [`health`] from the robot loop to get that transition.
+///
+/// [`update`]: HealthMonitor::update
+/// [`health`]: HealthMonitor::health
+pub struct HealthMonitor {
+    state: Mutex<State>,
+    callbacks: Mutex<Vec<Callback>>,
+}
+
+impl Default for HealthMonitor {
+    fn default() -> Self {
+        Self::new(DEFAULT_STALE_TIMEOUT)
+    }
+}
+
+impl HealthMonitor {
+    /// Create a monitor which reports the board as disconnected once no fresh data has been seen
+    /// for `stale_timeout`.
+    pub fn new(stale_timeout: Duration) -> Self {
+        Self {
+            state: Mutex::new(State {
+                health: Health::Connecting,
+                stale_timeout,
+                disconnect_errors: DEFAULT_DISCONNECT_ERRORS,
+                last_fresh: None,
+                last_timestamp: None,
+                status: None,
+                consecutive_errors: 0,
+            }),
+            callbacks: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Set the number of consecutive read errors before the board is considered disconnected.
+    pub fn with_disconnect_errors(self, errors: usize) -> Self {
+        self.state.lock().disconnect_errors = errors.max(1);
+        self
+    }
+
+    pub fn set_stale_timeout(&self, stale_timeout: Duration) {
+        self.state.lock().stale_timeout = stale_timeout;
+        self.update(Instant::now());
+    }
+
+    /// Call a function with the old and new state on every transition. Callbacks run on whichever
+    /// thread caused the transition. A transition to [`Health::Disconnected`] caused by stale data
+    /// is only seen once the monitor is queried, see [`HealthMonitor`].
+    pub fn on_transition<F: 'static + Fn(Health, Health) + Send>(&self, f: F) {
+        self.callbacks.lock().push(Box::new(f));
+    }
+
+    /// Record a sample read at `now`. A sample only counts as fresh if its board timestamp differs
+    /// from the previous one. Samples without a board timestamp are always fresh.
+    pub fn observe_sample(&self, board_timestamp: Option<u32>, now: Instant) {
+        {
+            let mut state = self.state.lock();
+            state.consecutive_errors = 0;
+
+            if board_timestamp.is_none() || board_timestamp != state.last_timestamp {
+                state.last_fresh = Some(now);
+                state.last_timestamp = board_timestamp;
+            }
+        }
+
+        self.update(now);
+    }
+
+    /// Record the operating state reported by the board.
+    pub fn observe_status<S: StatusSource>(&self, status: &S) {
+        self.state.lock().status = Some((status.operation_status(), status.calibration_status()));
+        self.update(Instant::now());
+    }
+
+    /// Record a failed read. Interrupted errors are ignored.
+    pub fn observe_error(&self, err: &io::Error) {
+        if err.kind() == io::ErrorKind::Interrupted {
+            return;
+        }
+
+        self.state.lock().consecutive_errors += 1;
+        self.update(Instant::now());
+    }
+
+    /// Re-evaluate the state at the given time. Staleness is only detected when this is called, so
+    /// the query methods below call it automatically. Nothing calls it once the samples stop
+    /// coming in.
+    pub fn update(&self, now: Instant) -> Health {
+        let (old, new) = {
+            let mut state = self.state.lock();
+            let old = state.health;
+            state.health = state.evaluate(now);
+            (old, state.health)
+        };
+
+        // Callbacks are run without holding the state lock so they can query the monitor
+        if old != new {
+            for callback in self.callbacks.lock().iter() {
+                callback(old, new);
+            }
+        }
+
+        new
+    }
+
+    pub fn health(&self) -> Health {
+        self.update(Instant::now())
+    }
+
+    /// True if fresh data has been received within the staleness timeout.
+    pub fn is_connected(&self) -> bool {
+        self.update(Instant::now());
+        self.state.lock().is_fresh(Instant::now())
+    }
+
+    /// True while the board is starting up or calibrating.
+    pub fn is_calibrating(&self) -> bool {
+        matches!(self.health(), Health::Initializing | Health::Calibrating)
+    }
+
+    /// The time since the last fresh sample
+    pub fn time_since_fresh(&self) -> Option<Duration> {
+        self.state
+            .lock()
+            .last_fresh
+            .map(|time| Instant::now().saturating_duration_since(time))
+    }
+
+    /// Feed this monitor with the samples and errors of a watcher. Use a watcher synced to the
+    /// board timestamp so stale data can be detected. The watcher only updates the monitor while
+    /// it is running, so a watcher that stopped is only noticed when the monitor is queried.
+    pub fn attach<T, S>(self: &Arc<Self>, watcher: &Watcher<T, S>) {
+        let monitor = self.clone();
+        watcher.on_sample(move |sample: &Sample<T>| {
+            monitor.observe_sample(sample.board_timestamp, sample.host_time)
+        });
+
+        let monitor = self.clone();
+        watcher.on_error(move |err| monitor.observe_error(err));
+    }
+
+    /// Feed this monitor with the status samples read by a watcher.
+    pub fn attach_status<T: StatusSource, S>(self: &Arc<Self>, watcher: &Watcher<T, S>) {
+        let monitor = self.clone();
+        watcher.on_sample(move |sample: &Sample<T>| monitor.observe_status(&sample.value));
+    }
+}
@@ -0,0 +1,212 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A fixed size history of timestamped samples. This makes it possible to look up what a value was
+//! at some point in the recent past, for example when fusing a vision measurement that was
+//! captured a few frames ago.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::heading::ContinuousYaw;
+use crate::math::Quaternion;
+use crate::register::storage::Orientation;
+use crate::serde::Vector;
+use crate::watch::Sample;
+
+/// Values which can be blended between two samples.
+pub trait Interpolate {
+    /// Blend between `self` (t = 0) and `other` (t = 1).
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for f64 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * f64::from(t)
+    }
+}
+
+impl<T: Interpolate> Interpolate for Vector<T> {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        Vector {
+            x: self.x.interpolate(&other.x, t),
+            y: self.y.interpolate(&other.y, t),
+            z: self.z.interpolate(&other.z, t),
+        }
+    }
+}
+
+impl Interpolate for Quaternion {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self.slerp(other, t)
+    }
+}
+
+impl Interpolate for ContinuousYaw {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        ContinuousYaw::from_angle(self.angle.interpolate(&other.angle, t))
+    }
+}
+
+impl Interpolate for Orientation {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        let timestamp = f64::from(self.timestamp).interpolate(&f64::from(other.timestamp), t);
+
+        Orientation {
+            timestamp: timestamp.round() as u32,
+            yaw: interpolate_degrees(self.yaw, other.yaw, t, -180.0),
+            roll: interpolate_degrees(self.roll, other.roll, t, -180.0),
+            pitch: interpolate_degrees(self.pitch, other.pitch, t, -180.0),
+            compass_heading: interpolate_degrees(
+                self.compass_heading,
+                other.compass_heading,
+                t,
+                0.0,
+            ),
+            fused_heading: interpolate_degrees(self.fused_heading, other.fused_heading, t, 0.0),
+        }
+    }
+}
+
+/// Interpolate between two angles in degrees along the shortest path. The result is wrapped into
+/// [min, min + 360).
+pub fn interpolate_degrees(a: f32, b: f32, t: f32, min: f32) -> f32 {
+    let delta = (b - a + 180.0).rem_euclid(360.0) - 180.0;
+    (a + delta * t - min).rem_euclid(360.0) + min
+}
+
+/// A ring buffer of samples ordered by the time they were read. Once full, the oldest sample is
+/// evicted for every new one so the cost of adding a sample stays constant.
+pub struct History<T> {
+    samples: VecDeque<Sample<T>>,
+    capacity: usize,
+    max_age: Option<Duration>,
+}
+
+impl<T> History<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            max_age: None,
+        }
+    }
+
+    /// Also evict samples that are older than `max_age` relative to the newest sample.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Add a sample to the history. Samples older than the newest sample are ignored to keep the
+    /// history ordered.
+    pub fn push(&mut self, sample: Sample<T>) {
+        if let Some(newest) = self.samples.back() {
+            if sample.host_time < newest.host_time {
+                return;
+            }
+        }
+
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+
+        if let Some(max_age) = self.max_age {
+            while let Some(oldest) = self.samples.front() {
+                if sample.host_time.duration_since(oldest.host_time) <= max_age {
+                    break;
+                }
+                self.samples.pop_front();
+            }
+        }
+
+        self.samples.push_back(sample);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Iterate through all samples from oldest to newest.
+    pub fn iter(&self) -> impl Iterator<Item = &Sample<T>> {
+        self.samples.iter()
+    }
+
+    /// Iterate through all samples from oldest to newest, allowing their values to be corrected.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Sample<T>> {
+        self.samples.iter_mut()
+    }
+
+    pub fn oldest(&self) -> Option<&Sample<T>> {
+        self.samples.front()
+    }
+
+    pub fn latest(&self) -> Option<&Sample<T>> {
+        self.samples.back()
+    }
+
+    /// The index of the first sample read after `time`
+    fn index_after(&self, time: Instant) -> usize {
+        self.samples.partition_point(|x| x.host_time <= time)
+    }
+
+    /// The newest sample read at or before `time`.
+    pub fn latest_before(&self, time: Instant) -> Option<&Sample<T>> {
+        match self.index_after(time) {
+            0 => None,
+            idx => self.samples.get(idx - 1),
+        }
+    }
+
+    /// All samples read between `start` and `end` (inclusive) from oldest to newest.
+    pub fn between(&self, start: Instant, end: Instant) -> impl Iterator<Item = &Sample<T>> {
+        let first = self.samples.partition_point(|x| x.host_time < start);
+        let last = self.index_after(end).max(first);
+
+        self.samples.range(first..last)
+    }
+}
+
+impl<T: Clone + Interpolate> History<T> {
+    /// Estimate the value at `time` by interpolating between the samples on either side of it. If
+    /// `time` is newer than every sample, the latest value is returned. Returns `None` if `time` is
+    /// older than every sample in the history.
+    pub fn at(&self, time: Instant) -> Option<T> {
+        let before = self.latest_before(time)?;
+
+        let after = match self.samples.get(self.index_after(time)) {
+            Some(x) => x,
+            None => return Some(before.value.clone()),
+        };
+
+        let span = after
+            .host_time
+            .duration_since(before.host_time)
+            .as_secs_f32();
+        let t = time.duration_since(before.host_time).as_secs_f32() / span;
+
+        Some(before.value.interpolate(&after.value, t))
+    }
+}
@@ -9,14 +9,41 @@
 #[macro_use]
 extern crate bitflags;
 
+use crate::convention::{Convert, Converted, CoordinateConvention};
+use crate::register::storage::{Config, Identity, Status, NAVX_IDENTITY};
 use crate::register::RegisterIO;
-use std::io;
+use crate::serde::{Capability, OmniMountConfig};
+use crate::serial::packet::PacketReader;
+use std::io::{self, ErrorKind};
 use std::ops::{Deref, DerefMut};
 use wpilib::spi::Spi;
 
+pub mod ahrs;
+pub mod bias;
+pub mod cell;
+pub mod collision;
+pub mod convention;
+pub mod estimator;
+pub mod field;
+pub mod heading;
+pub mod health;
+pub mod history;
+pub mod math;
+pub mod motion;
+pub mod mount;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+pub mod odometry;
+pub mod rate;
 pub mod register;
+pub mod schedule;
 pub mod serde;
 pub mod serial;
+pub mod stats;
+pub mod subscribe;
+pub mod thread;
+#[cfg(feature = "units")]
+pub mod units;
 pub mod watch;
 
 pub trait FromBufferFallible: Sized {
@@ -45,15 +72,76 @@ pub trait Packet {
 pub struct NavX<T> {
     inner: T,
     spec: BoardSpec,
+    convention: CoordinateConvention,
 }
 
 impl<T> NavX<T> {
     /// Retrieve board specs
-    pub fn init(io: T) -> Self {
-        unimplemented!()
+    pub fn init(mut io: T) -> io::Result<Self>
+    where
+        T: Request<Identity> + Request<Config> + Request<Status>,
+    {
+        let identity: Identity = io.read()?;
+        if identity.identity != NAVX_IDENTITY {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Board did not identify as a navX",
+            ));
+        }
+
+        let config: Config = io.read()?;
+        let status: Status = io.read()?;
+
+        Ok(Self {
+            inner: io,
+            spec: BoardSpec::new(identity, config, status.capabilities),
+            convention: CoordinateConvention::Native,
+        })
+    }
+
+    pub fn spec(&self) -> &BoardSpec {
+        &self.spec
+    }
+
+    /// Choose the coordinate convention used by [`read_converted`] and [`into_converted`]. Values
+    /// read directly from the inner connection are always in the frame of the board.
+    ///
+    /// [`read_converted`]: NavX::read_converted
+    /// [`into_converted`]: NavX::into_converted
+    pub fn set_convention(&mut self, convention: CoordinateConvention) {
+        self.convention = convention;
+    }
+
+    pub fn convention(&self) -> CoordinateConvention {
+        self.convention
     }
 
-    pub fn new_spi(mut port: Spi) -> Self {
+    /// Convert a value read from the board into the selected convention.
+    pub fn convert<V: Convert>(&self, value: V) -> V {
+        value.convert(self.convention)
+    }
+
+    /// Read a value and convert it into the selected convention.
+    pub fn read_converted<V: Convert>(&mut self) -> io::Result<V>
+    where
+        T: Request<V>,
+    {
+        Ok(self.inner.read()?.convert(self.convention))
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Give up the handle for a connection that converts every value into the selected
+    /// convention, for example to be watched.
+    pub fn into_converted(self) -> Converted<T> {
+        Converted::new(self.inner, self.convention)
+    }
+}
+
+impl NavX<RegisterIO<Spi>> {
+    pub fn new_spi(mut port: Spi) -> io::Result<Self> {
         const DEFAULT_BITRATE: u32 = 500000; // TODO: Find correct rate
 
         port.set_clock_rate(f64::from(DEFAULT_BITRATE));
@@ -80,7 +168,30 @@ impl<T> DerefMut for NavX<T> {
 }
 
 /// Storage for the different status flags and capabilities of this board
-pub struct BoardSpec {}
+#[derive(Copy, Clone, Debug)]
+pub struct BoardSpec {
+    pub identity: Identity,
+    pub config: Config,
+    pub capabilities: Capability,
+    /// The configured up axis, or `None` if the board does not support OmniMount
+    pub omnimount: Option<OmniMountConfig>,
+}
+
+impl BoardSpec {
+    pub fn new(identity: Identity, config: Config, capabilities: Capability) -> Self {
+        Self {
+            identity,
+            config,
+            capabilities,
+            omnimount: capabilities.omnimount_config(),
+        }
+    }
+
+    /// Move a raw reading into the frame of the fused outputs. See [`mount`] for details.
+    pub fn remap<V: mount::Remap>(&self, value: V) -> V {
+        value.remap(self.omnimount.unwrap_or_default())
+    }
+}
 
 // TODO: Replace with alternate trait that works better
 //pub trait BoardIO {
@@ -96,9 +207,44 @@ pub struct SerialIO<T> {
     inner: T,
 }
 
+impl<T> SerialIO<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    /// Read decoded packets from this connection one at a time.
+    pub fn into_reader(self) -> PacketReader<T>
+    where
+        T: io::Read,
+    {
+        PacketReader::new(self.inner)
+    }
+
+    /// A stream of every packet decoded from this connection.
+    #[cfg(feature = "async")]
+    pub fn into_stream(self) -> nonblocking::UpdateStream<T>
+    where
+        T: futures_io::AsyncRead + Unpin,
+    {
+        nonblocking::UpdateStream::new(self.inner)
+    }
+}
+
 pub trait Request<T> {
     /// Request to read a value. This operation is blocking!
     fn read(&mut self) -> io::Result<T>;
+
+    /// Check that the connection is usable again after a failed read. Watchers call this before
+    /// retrying, so it is the place to re-run any initialization or identity checks.
+    fn recover(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// The rate in Hz at which new values become available, if the connection knows it. Watchers
+    /// poll at this rate unless they are given a period.
+    fn update_rate(&mut self) -> Option<u32> {
+        None
+    }
 }
 
 const CRC7_POLY: u8 = 0x91;
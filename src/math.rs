@@ -0,0 +1,297 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Shared math for orientations and vectors reported by the navX.
+
+use crate::serde::Vector;
+use std::ops::{Add, Mul, Neg, Sub};
+
+impl Vector<f32> {
+    pub fn zero() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(self, other: Self) -> Self {
+        Self {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+
+    pub fn norm(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /// Scale this vector to a length of 1. A zero vector is returned unchanged.
+    pub fn normalize(self) -> Self {
+        let norm = self.norm();
+
+        if norm == 0.0 {
+            return self;
+        }
+
+        self * norm.recip()
+    }
+}
+
+impl Add for Vector<f32> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl Sub for Vector<f32> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+impl Mul<f32> for Vector<f32> {
+    type Output = Self;
+
+    fn mul(self, rhs: f32) -> Self::Output {
+        Self::new(self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+impl Neg for Vector<f32> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self::new(-self.x, -self.y, -self.z)
+    }
+}
+
+/// A row-major 3x3 rotation matrix.
+pub type Matrix3 = [[f32; 3]; 3];
+
+/// An orientation stored as a quaternion. The navX reports a unit quaternion that rotates vectors
+/// from the board frame into the world frame.
+#[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Quaternion {
+    /// The quaternion representing no rotation.
+    pub const IDENTITY: Self = Self {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        Self { w, x, y, z }
+    }
+
+    /// Create a rotation of `angle` radians around `axis`.
+    pub fn from_axis_angle(axis: Vector<f32>, angle: f32) -> Self {
+        let axis = axis.normalize();
+        let (sin, cos) = (angle / 2.0).sin_cos();
+
+        Self::new(cos, axis.x * sin, axis.y * sin, axis.z * sin)
+    }
+
+    /// Build a quaternion from Tait-Bryan angles in degrees. The rotation is applied in z-y-x
+    /// order: yaw around z, then pitch around y, then roll around x.
+    pub fn from_yaw_pitch_roll(yaw: f32, pitch: f32, roll: f32) -> Self {
+        let (sy, cy) = (yaw.to_radians() / 2.0).sin_cos();
+        let (sp, cp) = (pitch.to_radians() / 2.0).sin_cos();
+        let (sr, cr) = (roll.to_radians() / 2.0).sin_cos();
+
+        Self {
+            w: cr * cp * cy + sr * sp * sy,
+            x: sr * cp * cy - cr * sp * sy,
+            y: cr * sp * cy + sr * cp * sy,
+            z: cr * cp * sy - sr * sp * cy,
+        }
+    }
+
+    /// The orientation with no yaw whose gravity matches an accelerometer reading taken at rest.
+    /// Rotating the reading by the result points it straight up. A zero reading gives the
+    /// identity.
+    pub fn level(accel: Vector<f32>) -> Self {
+        if accel.norm() == 0.0 {
+            return Self::IDENTITY;
+        }
+
+        let roll = accel.y.atan2(accel.z);
+        let pitch = (-accel.x).atan2(accel.y.hypot(accel.z));
+
+        Self::from_yaw_pitch_roll(0.0, pitch.to_degrees(), roll.to_degrees())
+    }
+
+    /// Convert this quaternion to Tait-Bryan angles in degrees. The returned tuple is
+    /// (yaw, pitch, roll) using the same z-y-x order as [`Quaternion::from_yaw_pitch_roll`]. Yaw
+    /// and roll are in [-180, 180] and pitch is in [-90, 90].
+    pub fn to_yaw_pitch_roll(&self) -> (f32, f32, f32) {
+        let q = self.normalize();
+
+        let yaw = (2.0 * (q.w * q.z + q.x * q.y)).atan2(1.0 - 2.0 * (q.y * q.y + q.z * q.z));
+        // Clamp to protect against rounding errors near gimbal lock
+        let pitch = (2.0 * (q.w * q.y - q.z * q.x)).clamp(-1.0, 1.0).asin();
+        let roll = (2.0 * (q.w * q.x + q.y * q.z)).atan2(1.0 - 2.0 * (q.x * q.x + q.y * q.y));
+
+        (yaw.to_degrees(), pitch.to_degrees(), roll.to_degrees())
+    }
+
+    /// Build a quaternion from angles in degrees in the convention of the board: yaw clockwise
+    /// about Z, pitch about X with the nose up and roll about Y with the right side down. They are
+    /// applied in that order, as yaw, pitch and roll would be for a frame with X forward.
+    pub fn from_board_angles(yaw: f32, pitch: f32, roll: f32) -> Self {
+        let forward = Self::board_to_forward();
+        forward.conjugate() * Self::from_yaw_pitch_roll(-yaw, -pitch, roll) * forward
+    }
+
+    /// Convert this quaternion to angles in degrees in the convention of the board, the inverse of
+    /// [`Quaternion::from_board_angles`]. Yaw is in [-180, 180), pitch in [-90, 90] and roll in
+    /// [-180, 180].
+    pub fn to_board_angles(&self) -> (f32, f32, f32) {
+        let forward = Self::board_to_forward();
+        let (yaw, pitch, roll) = (forward * *self * forward.conjugate()).to_yaw_pitch_roll();
+
+        ((180.0 - yaw).rem_euclid(360.0) - 180.0, -pitch, roll)
+    }
+
+    /// Rotates vectors from the board frame into a frame with X forward, Y left and Z up, where
+    /// the z-y-x angles of [`Quaternion::from_yaw_pitch_roll`] are yaw, pitch and roll.
+    fn board_to_forward() -> Self {
+        Self::from_axis_angle(Vector::new(0.0, 0.0, 1.0), -std::f32::consts::FRAC_PI_2)
+    }
+
+    pub fn dot(&self, other: &Self) -> f32 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn norm(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /// Scale this quaternion to unit length. The board output is only approximately normalized
+    /// after being quantized, so this should be applied before using it as a rotation. A zero
+    /// quaternion is treated as the identity.
+    pub fn normalize(&self) -> Self {
+        let norm = self.norm();
+
+        if norm == 0.0 {
+            return Self::IDENTITY;
+        }
+
+        let inv = norm.recip();
+        Self::new(self.w * inv, self.x * inv, self.y * inv, self.z * inv)
+    }
+
+    /// The conjugate of this quaternion. For a unit quaternion this is the inverse rotation.
+    pub fn conjugate(&self) -> Self {
+        Self::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    /// Spherical linear interpolation between `self` (t = 0) and `other` (t = 1). The shortest
+    /// path is always taken.
+    pub fn slerp(&self, other: &Self, t: f32) -> Self {
+        let a = self.normalize();
+        let mut b = other.normalize();
+        let mut cos = a.dot(&b);
+
+        // q and -q represent the same rotation, so flip one to take the short way around
+        if cos < 0.0 {
+            b = Self::new(-b.w, -b.x, -b.y, -b.z);
+            cos = -cos;
+        }
+
+        // Fall back to a normalized lerp when the angle is too small for sin to be stable
+        let (wa, wb) = if cos > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+
+        Self::new(
+            wa * a.w + wb * b.w,
+            wa * a.x + wb * b.x,
+            wa * a.y + wb * b.y,
+            wa * a.z + wb * b.z,
+        )
+        .normalize()
+    }
+
+    /// The rotation matrix equivalent to this quaternion. Multiplying a column vector by the
+    /// result has the same effect as [`Quaternion::rotate`].
+    pub fn rotation_matrix(&self) -> Matrix3 {
+        let Self { w, x, y, z } = self.normalize();
+
+        [
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+            ],
+        ]
+    }
+
+    /// Rotate a vector by this quaternion. With a board orientation this converts a vector from
+    /// the body frame to the world frame. Use the conjugate to go the other way.
+    pub fn rotate(&self, v: Vector<f32>) -> Vector<f32> {
+        let q = self.normalize();
+        let u = Vector::new(q.x, q.y, q.z);
+
+        // v' = v + 2w(u x v) + 2u x (u x v)
+        let t = u.cross(v) * 2.0;
+        v + t * q.w + u.cross(t)
+    }
+
+    /// The direction of gravity in the body frame, in G. A board lying flat reports (0, 0, 1).
+    /// Subtracting this from the raw accelerometer reading gives the linear acceleration.
+    pub fn gravity(&self) -> Vector<f32> {
+        self.conjugate().rotate(Vector::new(0.0, 0.0, 1.0))
+    }
+}
+
+impl Mul for Quaternion {
+    type Output = Self;
+
+    /// The Hamilton product. `a * b` applies the rotation `b` first, then `a`.
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self {
+            w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        }
+    }
+}
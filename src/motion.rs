@@ -0,0 +1,340 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Motion and rotation detection. This is the equivalent of `isMoving()` and `isRotating()` in the
+//! official navX libraries. The `MOVING` and `YAW_STABLE` flags reported by the board are combined
+//! with thresholds on the samples seen by the host, so motion is still detected when only one of
+//! them is available.
+
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::collision::LinearAccelSource;
+use crate::rate::AngularRate;
+use crate::register::storage;
+use crate::serde::SensorStatus;
+use crate::serial;
+use crate::serial::storage::PositionUpdate;
+use crate::subscribe::{Capacity, Subscribers, Subscription};
+use crate::watch::{Sample, Watcher};
+
+/// Samples which report the sensor flags of the board.
+pub trait SensorStatusSource {
+    fn sensor_status(&self) -> SensorStatus;
+}
+
+impl SensorStatusSource for SensorStatus {
+    fn sensor_status(&self) -> SensorStatus {
+        *self
+    }
+}
+
+impl SensorStatusSource for storage::Status {
+    fn sensor_status(&self) -> SensorStatus {
+        self.sensor_status
+    }
+}
+
+impl SensorStatusSource for serial::storage::Status {
+    fn sensor_status(&self) -> SensorStatus {
+        self.sensor
+    }
+}
+
+impl SensorStatusSource for PositionUpdate {
+    fn sensor_status(&self) -> SensorStatus {
+        self.status.sensor
+    }
+}
+
+/// Samples which report how fast the board is rotating.
+pub trait RotationSource {
+    /// The rotation rate in degrees per second. Only the size of the rate is used.
+    fn rotation_rate(&self) -> f32;
+}
+
+/// A yaw rate, such as the one estimated by [`YawDifferentiator`].
+///
+/// [`YawDifferentiator`]: crate::rate::YawDifferentiator
+impl RotationSource for f32 {
+    fn rotation_rate(&self) -> f32 {
+        self.abs()
+    }
+}
+
+impl RotationSource for AngularRate {
+    fn rotation_rate(&self) -> f32 {
+        self.body.norm()
+    }
+}
+
+/// The thresholds used to detect motion on the host. Each quantity has a higher threshold to start
+/// moving and a lower one to stop, so noise around a single threshold does not make the state
+/// flicker.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MotionThresholds {
+    /// Linear acceleration (G) above which the robot is moving
+    pub moving_accel: f32,
+    /// Linear acceleration (G) below which the robot may be stopped
+    pub stopped_accel: f32,
+    /// Rotation rate (degrees/sec) above which the robot is rotating
+    pub rotating_rate: f32,
+    /// Rotation rate (degrees/sec) below which the robot may have stopped rotating
+    pub stopped_rate: f32,
+    /// How long values must stay below the stopped thresholds before motion is considered over
+    pub settle_time: Duration,
+}
+
+impl Default for MotionThresholds {
+    fn default() -> Self {
+        Self {
+            moving_accel: 0.02,
+            stopped_accel: 0.01,
+            rotating_rate: 2.0,
+            stopped_rate: 1.0,
+            settle_time: Duration::from_millis(250),
+        }
+    }
+}
+
+/// A change between standing still and moving.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MotionEvent {
+    /// The robot has stopped moving and rotating
+    Stationary,
+    /// The robot has started moving or rotating
+    Moving,
+}
+
+/// A value that turns on above one threshold and only turns off after staying below another for a
+/// while.
+#[derive(Default)]
+struct Hysteresis {
+    active: bool,
+    last_above: Option<Sample<()>>,
+}
+
+impl Hysteresis {
+    fn update(&mut self, sample: Sample<f32>, start: f32, stop: f32, settle: Duration) -> bool {
+        if sample.value > start {
+            self.active = true;
+        }
+
+        if sample.value >= stop {
+            self.last_above = Some(sample.with_value(()));
+        } else if self.active {
+            let settled = match self.last_above {
+                Some(last) => sample.seconds_since(&last) >= settle.as_secs_f64(),
+                None => true,
+            };
+
+            if settled {
+                self.active = false;
+            }
+        }
+
+        self.active
+    }
+}
+
+struct State {
+    thresholds: MotionThresholds,
+    /// The latest flags reported by the board, if any have been seen
+    sensor: Option<SensorStatus>,
+    accel: Option<Hysteresis>,
+    rate: Option<Hysteresis>,
+    stationary: Option<bool>,
+}
+
+impl State {
+    fn is_moving(&self) -> bool {
+        self.sensor
+            .is_some_and(|x| x.contains(SensorStatus::MOVING))
+            || self.accel.as_ref().is_some_and(|x| x.active)
+    }
+
+    fn is_rotating(&self) -> bool {
+        self.sensor
+            .is_some_and(|x| !x.contains(SensorStatus::YAW_STABLE))
+            || self.rate.as_ref().is_some_and(|x| x.active)
+    }
+
+    /// Whether the robot is stationary, or `None` if nothing has been observed yet
+    fn stationary(&self) -> Option<bool> {
+        if self.sensor.is_none() && self.accel.is_none() && self.rate.is_none() {
+            return None;
+        }
+
+        Some(!self.is_moving() && !self.is_rotating())
+    }
+}
+
+/// Tracks whether the robot is moving or rotating. A detector can be fed by hand or attached to
+/// watchers so it is updated as new samples come in. It is meant to be shared through an `Arc`.
+pub struct MotionDetector {
+    state: Mutex<State>,
+    subscribers: Subscribers<MotionEvent>,
+}
+
+impl Default for MotionDetector {
+    fn default() -> Self {
+        Self::new(MotionThresholds::default())
+    }
+}
+
+impl MotionDetector {
+    pub fn new(thresholds: MotionThresholds) -> Self {
+        Self {
+            state: Mutex::new(State {
+                thresholds,
+                sensor: None,
+                accel: None,
+                rate: None,
+                stationary: None,
+            }),
+            subscribers: Subscribers::new(),
+        }
+    }
+
+    pub fn set_thresholds(&self, thresholds: MotionThresholds) {
+        self.state.lock().thresholds = thresholds;
+    }
+
+    pub fn thresholds(&self) -> MotionThresholds {
+        self.state.lock().thresholds
+    }
+
+    /// Record the sensor flags reported by the board.
+    pub fn observe_status<S: SensorStatusSource>(&self, sample: &Sample<S>) {
+        let sample = sample.with_value(sample.value.sensor_status());
+        self.state.lock().sensor = Some(sample.value);
+        self.update(sample);
+    }
+
+    /// Record the linear acceleration of the robot.
+    pub fn observe_accel<T: LinearAccelSource>(&self, sample: &Sample<T>) {
+        let sample = sample.with_value(sample.value.linear_accel().norm());
+
+        {
+            let mut state = self.state.lock();
+            let thresholds = state.thresholds;
+            state.accel.get_or_insert_with(Hysteresis::default).update(
+                sample,
+                thresholds.moving_accel,
+                thresholds.stopped_accel,
+                thresholds.settle_time,
+            );
+        }
+
+        self.update(sample);
+    }
+
+    /// Record the rotation rate of the robot.
+    pub fn observe_rate<R: RotationSource>(&self, sample: &Sample<R>) {
+        let sample = sample.with_value(sample.value.rotation_rate());
+
+        {
+            let mut state = self.state.lock();
+            let thresholds = state.thresholds;
+            state.rate.get_or_insert_with(Hysteresis::default).update(
+                sample,
+                thresholds.rotating_rate,
+                thresholds.stopped_rate,
+                thresholds.settle_time,
+            );
+        }
+
+        self.update(sample);
+    }
+
+    /// Publish an event if the robot started or stopped moving.
+    fn update<U>(&self, sample: Sample<U>) {
+        let event = {
+            let mut state = self.state.lock();
+            let stationary = state.stationary();
+
+            if stationary == state.stationary {
+                return;
+            }
+            state.stationary = stationary;
+
+            match stationary {
+                Some(true) => MotionEvent::Stationary,
+                Some(false) => MotionEvent::Moving,
+                None => return,
+            }
+        };
+
+        // Subscribers are run without holding the state lock so they can query the detector
+        self.subscribers.publish(&sample.with_value(event));
+    }
+
+    /// True if the board reports motion or the linear acceleration is above the threshold.
+    pub fn is_moving(&self) -> bool {
+        self.state.lock().is_moving()
+    }
+
+    /// True if the board reports an unstable yaw or the rotation rate is above the threshold.
+    pub fn is_rotating(&self) -> bool {
+        self.state.lock().is_rotating()
+    }
+
+    /// True if the yaw can be trusted not to be changing. This is false until the board or a rate
+    /// sample has confirmed it.
+    pub fn is_yaw_stable(&self) -> bool {
+        let state = self.state.lock();
+        (state.sensor.is_some() || state.rate.is_some()) && !state.is_rotating()
+    }
+
+    /// True if the robot is neither moving nor rotating. This is false until a sample has been
+    /// observed.
+    pub fn is_stationary(&self) -> bool {
+        self.state.lock().stationary() == Some(true)
+    }
+
+    /// Forget everything observed so far.
+    pub fn reset(&self) {
+        let mut state = self.state.lock();
+        state.sensor = None;
+        state.accel = None;
+        state.rate = None;
+        state.stationary = None;
+    }
+
+    /// Receive every change between moving and stationary from now on.
+    pub fn subscribe(&self, capacity: Capacity) -> Subscription<MotionEvent> {
+        self.subscribers.subscribe(capacity)
+    }
+
+    /// Call a function on every change between moving and stationary from now on. The function is
+    /// run on the thread feeding the detector, so it should return quickly.
+    pub fn on_event<F: 'static + Fn(&Sample<MotionEvent>) + Send>(&self, f: F) {
+        self.subscribers.listen(Box::new(move |event| {
+            f(event);
+            true
+        }));
+    }
+
+    /// Feed this detector with the sensor flags read by a watcher.
+    pub fn attach_status<T: SensorStatusSource, S>(self: &Arc<Self>, watcher: &Watcher<T, S>) {
+        let detector = self.clone();
+        watcher.on_sample(move |sample: &Sample<T>| detector.observe_status(sample));
+    }
+
+    /// Feed this detector with the linear acceleration read by a watcher.
+    pub fn attach_accel<T: LinearAccelSource, S>(self: &Arc<Self>, watcher: &Watcher<T, S>) {
+        let detector = self.clone();
+        watcher.on_sample(move |sample: &Sample<T>| detector.observe_accel(sample));
+    }
+
+    /// Feed this detector with the rotation rates read by a watcher.
+    pub fn attach_rate<T: RotationSource, S>(self: &Arc<Self>, watcher: &Watcher<T, S>) {
+        let detector = self.clone();
+        watcher.on_sample(move |sample: &Sample<T>| detector.observe_rate(sample));
+    }
+}
@@ -0,0 +1,397 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! How the board is mounted on the robot.
+//!
+//! OmniMount lets the board be mounted with any of its axes pointing up. The board applies the
+//! configuration to its own fused outputs such as yaw and the quaternion, but raw sensor readings
+//! are still reported along the axes printed on the board. The remapping here moves them into the
+//! same frame as the fused outputs, with the configured up axis becoming Z.
+//!
+//! Boards mounted at any other angle, such as on a turret plate or tilted on a bracket, are
+//! handled on the host with a [`MountingRotation`], which moves every output from the board frame
+//! into the robot frame.
+
+use std::io;
+
+use crate::math::Quaternion;
+use crate::rate::AngularRate;
+use crate::register::storage::{LinearAccel, Orientation, RawGyro};
+use crate::serde::{OmniMountConfig, Vector};
+use crate::serial::storage::{DirectionalUpdate, PositionUpdate, RawDataUpdate};
+use crate::watch::{Watch, Watched};
+use crate::Request;
+
+/// Vector components which can change sign when an axis is flipped.
+pub trait Flip: Copy {
+    fn flip(self) -> Self;
+}
+
+/// Raw readings saturate instead of overflowing when the most negative value is flipped.
+impl Flip for i16 {
+    fn flip(self) -> Self {
+        self.saturating_neg()
+    }
+}
+
+impl Flip for f32 {
+    fn flip(self) -> Self {
+        -self
+    }
+}
+
+impl Flip for f64 {
+    fn flip(self) -> Self {
+        -self
+    }
+}
+
+impl OmniMountConfig {
+    /// The axis of the board that points up, as a unit vector along the printed axes. Yaw is the
+    /// rotation around this axis.
+    pub fn up_axis(&self) -> Vector<f32> {
+        match self {
+            OmniMountConfig::Default | OmniMountConfig::ZUp => Vector::new(0.0, 0.0, 1.0),
+            OmniMountConfig::ZDown => Vector::new(0.0, 0.0, -1.0),
+            OmniMountConfig::XUp => Vector::new(1.0, 0.0, 0.0),
+            OmniMountConfig::XDown => Vector::new(-1.0, 0.0, 0.0),
+            OmniMountConfig::YUp => Vector::new(0.0, 1.0, 0.0),
+            OmniMountConfig::YDown => Vector::new(0.0, -1.0, 0.0),
+        }
+    }
+
+    /// Rotate a vector from the printed axes of the board so the up axis becomes Z. The other two
+    /// axes follow in a right handed order, so this is always a proper rotation.
+    pub fn remap<T: Flip>(&self, v: Vector<T>) -> Vector<T> {
+        let Vector { x, y, z } = v;
+
+        match self {
+            OmniMountConfig::Default | OmniMountConfig::ZUp => v,
+            OmniMountConfig::ZDown => Vector::new(x, y.flip(), z.flip()),
+            OmniMountConfig::XUp => Vector::new(y, z, x),
+            OmniMountConfig::XDown => Vector::new(y, z.flip(), x.flip()),
+            OmniMountConfig::YUp => Vector::new(z, x, y),
+            OmniMountConfig::YDown => Vector::new(z, x.flip(), y.flip()),
+        }
+    }
+}
+
+/// Values measured along the axes of the board.
+pub trait Remap {
+    /// Move this value into the frame used by the fused outputs of a board mounted with `config`.
+    fn remap(self, config: OmniMountConfig) -> Self;
+}
+
+impl<T: Flip> Remap for Vector<T> {
+    fn remap(self, config: OmniMountConfig) -> Self {
+        config.remap(self)
+    }
+}
+
+impl Remap for RawGyro {
+    fn remap(self, config: OmniMountConfig) -> Self {
+        Self {
+            gyro: config.remap(self.gyro),
+        }
+    }
+}
+
+impl Remap for RawDataUpdate {
+    fn remap(self, config: OmniMountConfig) -> Self {
+        Self {
+            gyro: config.remap(self.gyro),
+            acceleration: config.remap(self.acceleration),
+            magnetometer: config.remap(self.magnetometer),
+            temperature: self.temperature,
+        }
+    }
+}
+
+/// The world frame yaw rate is already independent of how the board is mounted.
+impl Remap for AngularRate {
+    fn remap(self, config: OmniMountConfig) -> Self {
+        Self {
+            body: config.remap(self.body),
+            yaw_rate: self.yaw_rate,
+        }
+    }
+}
+
+/// Remaps every value read from a provider to match the OmniMount configuration of the board.
+pub struct Remapped<S> {
+    inner: S,
+    config: OmniMountConfig,
+}
+
+impl<S> Remapped<S> {
+    pub fn new(inner: S, config: OmniMountConfig) -> Self {
+        Self { inner, config }
+    }
+
+    pub fn config(&self) -> OmniMountConfig {
+        self.config
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Request<T>, T: Remap> Request<T> for Remapped<S> {
+    fn read(&mut self) -> io::Result<T> {
+        Ok(self.inner.read()?.remap(self.config))
+    }
+
+    fn recover(&mut self) -> io::Result<()> {
+        self.inner.recover()
+    }
+
+    fn update_rate(&mut self) -> Option<u32> {
+        self.inner.update_rate()
+    }
+}
+
+impl<S, T> Watch<T> for Remapped<S>
+where
+    S: 'static + Request<T> + Send,
+    T: 'static + Remap + Send,
+{
+    type Provider = Self;
+
+    fn watch(self) -> Watched<T, Self::Provider> {
+        Watched::new(self)
+    }
+}
+
+/// The rotation of the board relative to the robot. Vectors measured along the axes of the board
+/// are rotated into the robot frame. The board and the robot share a world frame, so vectors in it
+/// are unchanged, while yaw and the headings all move by the yaw of the mounting.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MountingRotation {
+    /// Rotates vectors from the board frame into the robot frame
+    rotation: Quaternion,
+}
+
+impl MountingRotation {
+    /// Create a mounting from the rotation that takes vectors from the board frame into the robot
+    /// frame, which is the orientation of the board when the robot is level and facing forward.
+    pub fn new(rotation: Quaternion) -> Self {
+        Self {
+            rotation: rotation.normalize(),
+        }
+    }
+
+    /// Create a mounting from the angles of the board on the robot in degrees, in the convention
+    /// of the board described by [`Quaternion::from_board_angles`]. A board turned clockwise on a
+    /// turret plate has a positive yaw, and one mounted upside down has a roll of 180.
+    pub fn from_yaw_pitch_roll(yaw: f32, pitch: f32, roll: f32) -> Self {
+        Self::new(Quaternion::from_board_angles(yaw, pitch, roll))
+    }
+
+    pub fn rotation(&self) -> Quaternion {
+        self.rotation
+    }
+
+    /// The angles of the board on the robot in degrees, in the convention of the board
+    pub fn yaw_pitch_roll(&self) -> (f32, f32, f32) {
+        self.rotation.to_board_angles()
+    }
+
+    /// Adjust the pitch and roll of the mounting so that an accelerometer reading taken while the
+    /// robot sits level points straight up in the robot frame. The direction the board faces on
+    /// the robot is kept, since gravity says nothing about it. The reading can be in any unit.
+    pub fn calibrate_level(&mut self, accel: Vector<f32>) {
+        let heading = self.forward_heading();
+        let gravity = self.rotation.rotate(accel);
+        let rotation = Quaternion::level(gravity) * self.rotation;
+        let turn = heading - Self::new(rotation).forward_heading();
+
+        self.rotation = (Quaternion::from_board_angles(turn, 0.0, 0.0) * rotation).normalize();
+    }
+
+    /// The heading of the front of the board in the robot frame, in degrees clockwise. The board
+    /// zeroes its yaw and measures headings along this axis, so it is the yaw of the mounting even
+    /// when the board is upside down.
+    fn forward_heading(&self) -> f32 {
+        let forward = self.rotation.rotate(Vector::new(0.0, 1.0, 0.0));
+
+        if forward.x == 0.0 && forward.y == 0.0 {
+            return 0.0;
+        }
+
+        forward.x.atan2(forward.y).to_degrees()
+    }
+
+    /// Rotate a vector from the board frame into the robot frame.
+    pub fn body(&self, v: Vector<f32>) -> Vector<f32> {
+        self.rotation.rotate(v)
+    }
+
+    /// Rotate a raw reading from the board frame into the robot frame. Components saturate if the
+    /// rotated reading no longer fits.
+    pub fn body_raw(&self, v: Vector<i16>) -> Vector<i16> {
+        self.body(v.map(f32::from)).map(|x| x.round() as i16)
+    }
+
+    /// Turn the orientation of the board into the orientation of the robot.
+    pub fn orientation(&self, q: Quaternion) -> Quaternion {
+        (q * self.rotation.conjugate()).normalize()
+    }
+
+    /// Turn the yaw, pitch and roll of the board into those of the robot, in degrees. Both are in
+    /// the convention of the board.
+    pub fn yaw_pitch_roll_of(&self, yaw: f32, pitch: f32, roll: f32) -> (f32, f32, f32) {
+        self.orientation(Quaternion::from_board_angles(yaw, pitch, roll))
+            .to_board_angles()
+    }
+
+    /// Turn a heading of the board in [0, 360) into the heading of the front of the robot.
+    pub fn heading_of(&self, heading: f32) -> f32 {
+        (heading - self.forward_heading()).rem_euclid(360.0)
+    }
+}
+
+/// Outputs which can be moved from the board frame into the robot frame.
+pub trait ToRobotFrame {
+    fn to_robot_frame(self, mounting: &MountingRotation) -> Self;
+}
+
+impl ToRobotFrame for Quaternion {
+    fn to_robot_frame(self, mounting: &MountingRotation) -> Self {
+        mounting.orientation(self)
+    }
+}
+
+impl ToRobotFrame for RawGyro {
+    fn to_robot_frame(self, mounting: &MountingRotation) -> Self {
+        Self {
+            gyro: mounting.body_raw(self.gyro),
+        }
+    }
+}
+
+impl ToRobotFrame for RawDataUpdate {
+    fn to_robot_frame(self, mounting: &MountingRotation) -> Self {
+        Self {
+            gyro: mounting.body_raw(self.gyro),
+            acceleration: mounting.body_raw(self.acceleration),
+            magnetometer: mounting.body_raw(self.magnetometer),
+            temperature: self.temperature,
+        }
+    }
+}
+
+impl ToRobotFrame for AngularRate {
+    fn to_robot_frame(self, mounting: &MountingRotation) -> Self {
+        Self {
+            body: mounting.body(self.body),
+            yaw_rate: self.yaw_rate,
+        }
+    }
+}
+
+/// Linear acceleration is in the world frame, which the board and the robot share.
+impl ToRobotFrame for LinearAccel {
+    fn to_robot_frame(self, _mounting: &MountingRotation) -> Self {
+        self
+    }
+}
+
+impl ToRobotFrame for Orientation {
+    fn to_robot_frame(self, mounting: &MountingRotation) -> Self {
+        let (yaw, pitch, roll) = mounting.yaw_pitch_roll_of(self.yaw, self.pitch, self.roll);
+
+        Self {
+            yaw,
+            pitch,
+            roll,
+            compass_heading: mounting.heading_of(self.compass_heading),
+            fused_heading: mounting.heading_of(self.fused_heading),
+            ..self
+        }
+    }
+}
+
+impl ToRobotFrame for DirectionalUpdate {
+    fn to_robot_frame(self, mounting: &MountingRotation) -> Self {
+        let (yaw, pitch, roll) = mounting.yaw_pitch_roll_of(self.yaw, self.pitch, self.roll);
+
+        Self {
+            yaw,
+            pitch,
+            roll,
+            compass_heading: mounting.heading_of(self.compass_heading),
+        }
+    }
+}
+
+impl ToRobotFrame for PositionUpdate {
+    fn to_robot_frame(self, mounting: &MountingRotation) -> Self {
+        let (yaw, pitch, roll) = mounting.yaw_pitch_roll_of(self.yaw, self.pitch, self.roll);
+
+        Self {
+            yaw,
+            pitch,
+            roll,
+            compass_heading: mounting.heading_of(self.compass_heading),
+            fused_heading: mounting.heading_of(self.fused_heading),
+            quaternion: mounting.orientation(self.quaternion),
+            ..self
+        }
+    }
+}
+
+/// Moves every value read from a provider into the robot frame.
+pub struct Mounted<S> {
+    inner: S,
+    mounting: MountingRotation,
+}
+
+impl<S> Mounted<S> {
+    pub fn new(inner: S, mounting: MountingRotation) -> Self {
+        Self { inner, mounting }
+    }
+
+    pub fn mounting(&self) -> MountingRotation {
+        self.mounting
+    }
+
+    /// Change the mounting, for example after a level calibration.
+    pub fn set_mounting(&mut self, mounting: MountingRotation) {
+        self.mounting = mounting;
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Request<T>, T: ToRobotFrame> Request<T> for Mounted<S> {
+    fn read(&mut self) -> io::Result<T> {
+        Ok(self.inner.read()?.to_robot_frame(&self.mounting))
+    }
+
+    fn recover(&mut self) -> io::Result<()> {
+        self.inner.recover()
+    }
+
+    fn update_rate(&mut self) -> Option<u32> {
+        self.inner.update_rate()
+    }
+}
+
+impl<S, T> Watch<T> for Mounted<S>
+where
+    S: 'static + Request<T> + Send,
+    T: 'static + ToRobotFrame + Send,
+{
+    type Provider = Self;
+
+    fn watch(self) -> Watched<T, Self::Provider> {
+        Watched::new(self)
+    }
+}
@@ -0,0 +1,167 @@
+//! An in-memory stand-in for a board, useful for testing async code without hardware.
+
+use futures_io::{AsyncRead, AsyncWrite};
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use crate::get_crc;
+
+/// The register address is stored in the lower 7 bits of a request
+const ADDRESS_MASK: u8 = 0x7F;
+const WRITE_FLAG: u8 = 0x80;
+
+/// Behaves like a board on the other end of a non-blocking connection. Register reads are answered
+/// from an in-memory register map and any queued bytes are read back as if the board streamed
+/// them. Every read and write returns `Pending` once before completing so wakeups get exercised.
+pub struct FakeTransport {
+    registers: [u8; 0x100],
+    request: Vec<u8>,
+    incoming: VecDeque<u8>,
+    written: Vec<u8>,
+    waker: Option<Waker>,
+    yielded: bool,
+    closed: bool,
+}
+
+impl Default for FakeTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeTransport {
+    pub fn new() -> Self {
+        Self {
+            registers: [0; 0x100],
+            request: Vec::new(),
+            incoming: VecDeque::new(),
+            written: Vec::new(),
+            waker: None,
+            yielded: false,
+            closed: false,
+        }
+    }
+
+    /// Copy bytes into the register map starting at `address`.
+    pub fn set_registers(&mut self, address: u8, bytes: &[u8]) {
+        let start = usize::from(address);
+        self.registers[start..start + bytes.len()].copy_from_slice(bytes);
+    }
+
+    pub fn registers(&self) -> &[u8] {
+        &self.registers[..]
+    }
+
+    /// Queue bytes to be read as if they were sent by the board.
+    pub fn push_bytes(&mut self, bytes: &[u8]) {
+        self.incoming.extend(bytes);
+
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Hang up once the queued bytes have been read. Reads return end of file after that.
+    pub fn close(&mut self) {
+        self.closed = true;
+
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Every byte written to the transport so far
+    pub fn written(&self) -> &[u8] {
+        &self.written
+    }
+
+    /// Returns `Pending` every other call, scheduling the task to be polled again.
+    fn yield_once(&mut self, cx: &mut Context<'_>) -> bool {
+        self.yielded = !self.yielded;
+
+        if self.yielded {
+            cx.waker().wake_by_ref();
+        }
+        self.yielded
+    }
+
+    /// Answer every complete register request. Requests with a bad checksum are ignored.
+    fn handle_requests(&mut self) {
+        while self.request.len() >= 3 {
+            let packet: Vec<u8> = self.request.drain(..3).collect();
+
+            if get_crc(&packet, 2) != packet[2] {
+                continue;
+            }
+
+            let address = usize::from(packet[0] & ADDRESS_MASK);
+
+            if packet[0] & WRITE_FLAG != 0 {
+                self.registers[address] = packet[1];
+            } else {
+                let end = (address + usize::from(packet[1])).min(self.registers.len());
+                let response = &self.registers[address..end];
+                let crc = get_crc(response, response.len());
+
+                self.incoming.extend(response);
+                self.incoming.push_back(crc);
+            }
+        }
+    }
+}
+
+impl AsyncRead for FakeTransport {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.yield_once(cx) {
+            return Poll::Pending;
+        }
+
+        if self.incoming.is_empty() && self.closed {
+            return Poll::Ready(Ok(0));
+        }
+
+        if self.incoming.is_empty() {
+            self.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        let len = buf.len().min(self.incoming.len());
+        for (dst, src) in buf.iter_mut().zip(self.incoming.drain(..len)) {
+            *dst = src;
+        }
+
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl AsyncWrite for FakeTransport {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.yield_once(cx) {
+            return Poll::Pending;
+        }
+
+        self.written.extend_from_slice(buf);
+        self.request.extend_from_slice(buf);
+        self.handle_requests();
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
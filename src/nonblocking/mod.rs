@@ -0,0 +1,64 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Non-blocking versions of the transports for use from async code. Everything here is built on the
+//! `futures` io traits so it runs on any executor. Enable the `tokio` feature to use tokio's io
+//! types through [`Compat`].
+
+use std::future::Future;
+use std::io;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+#[cfg(feature = "tokio")]
+mod compat;
+pub mod fake;
+mod register;
+mod serial;
+
+#[cfg(feature = "tokio")]
+pub use self::compat::Compat;
+pub use self::register::AsyncRegisterIO;
+pub use self::serial::UpdateStream;
+
+/// The non-blocking equivalent of [`Request`](crate::Request).
+pub trait AsyncRequest<T> {
+    /// Attempt to read a value. If it is not ready yet, the current task is woken once progress
+    /// can be made. A read that has been started must be polled to completion before a different
+    /// value is requested.
+    fn poll_read(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<T>>;
+}
+
+/// Adds a `read` method which returns a future to every [`AsyncRequest`].
+pub trait AsyncRequestExt<T>: AsyncRequest<T> {
+    fn read(&mut self) -> ReadRequest<'_, Self, T>
+    where
+        Self: Sized,
+    {
+        ReadRequest {
+            request: self,
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<T, R: AsyncRequest<T>> AsyncRequestExt<T> for R {}
+
+/// The future returned by [`AsyncRequestExt::read`]
+pub struct ReadRequest<'a, R, T> {
+    request: &'a mut R,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<R: AsyncRequest<T>, T> Future for ReadRequest<'_, R, T> {
+    type Output = io::Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.request.poll_read(cx)
+    }
+}
@@ -0,0 +1,144 @@
+use futures_core::ready;
+use futures_io::{AsyncRead, AsyncWrite};
+use std::io::{self, ErrorKind};
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use crate::get_crc;
+use crate::nonblocking::AsyncRequest;
+use crate::register::storage::Addressable;
+use crate::stats::LinkStats;
+
+/// How far along the current transaction is
+#[derive(Copy, Clone)]
+enum State {
+    Idle,
+    Request { sent: usize },
+    Flush,
+    Response { received: usize },
+}
+
+/// The register protocol over a non-blocking connection.
+pub struct AsyncRegisterIO<T> {
+    inner: T,
+    buffer: [u8; 0x100],
+    state: State,
+    started: Instant,
+    stats: LinkStats,
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> AsyncRegisterIO<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            buffer: [0; 0x100],
+            state: State::Idle,
+            started: Instant::now(),
+            stats: LinkStats::new(),
+        }
+    }
+
+    /// A handle to the statistics of this connection
+    pub fn stats(&self) -> LinkStats {
+        self.stats.clone()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Send the request and read `len` bytes of response into the buffer
+    fn poll_transaction(
+        &mut self,
+        cx: &mut Context<'_>,
+        request: &[u8],
+        len: usize,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            match self.state {
+                State::Idle => {
+                    self.started = Instant::now();
+                    self.state = State::Request { sent: 0 };
+                }
+                State::Request { sent } => {
+                    let inner = Pin::new(&mut self.inner);
+                    let written = ready!(inner.poll_write(cx, &request[sent..]))?;
+                    if written == 0 {
+                        return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+                    }
+
+                    self.stats.record_sent(written);
+                    let sent = sent + written;
+
+                    self.state = if sent == request.len() {
+                        State::Flush
+                    } else {
+                        State::Request { sent }
+                    };
+                }
+                State::Flush => {
+                    ready!(Pin::new(&mut self.inner).poll_flush(cx))?;
+                    self.state = State::Response { received: 0 };
+                }
+                State::Response { received } if received == len => return Poll::Ready(Ok(())),
+                State::Response { received } => {
+                    let inner = Pin::new(&mut self.inner);
+                    match ready!(inner.poll_read(cx, &mut self.buffer[received..len]))? {
+                        0 => return Poll::Ready(Err(ErrorKind::UnexpectedEof.into())),
+                        x => {
+                            self.stats.record_received(x);
+                            self.state = State::Response {
+                                received: received + x,
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin, V: Addressable> AsyncRequest<V> for AsyncRegisterIO<T> {
+    fn poll_read(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<V>> {
+        let request = V::request().to_bytes();
+        let result = ready!(self.poll_transaction(cx, &request, V::LEN + 1));
+
+        // Start over on the next read whether this one succeeded or not
+        self.state = State::Idle;
+        result?;
+
+        self.stats.record_transaction();
+        self.stats.record_latency(self.started.elapsed());
+
+        // Checksum
+        if get_crc(&self.buffer[..V::LEN], V::LEN) != self.buffer[V::LEN] {
+            self.stats.record_crc_failure();
+            return Poll::Ready(Err(ErrorKind::InvalidData.into()));
+        }
+
+        // Parse bytes to type
+        Poll::Ready(match V::try_read(&self.buffer[..V::LEN]) {
+            Some(x) => Ok(x),
+            None => {
+                self.stats.record_decode_failure();
+                Err(ErrorKind::InvalidData.into())
+            }
+        })
+    }
+}
+
+impl<T> Deref for AsyncRegisterIO<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for AsyncRegisterIO<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
@@ -0,0 +1,83 @@
+use futures_core::{ready, Stream};
+use futures_io::AsyncRead;
+use std::io::{self, ErrorKind};
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use crate::serial::packet::{Framer, Update};
+use crate::stats::LinkStats;
+use crate::watch::Sample;
+
+/// A stream of every packet decoded from a serial connection. Bad packets are skipped and the
+/// stream ends when the connection does.
+pub struct UpdateStream<T> {
+    inner: T,
+    framer: Framer,
+}
+
+impl<T: AsyncRead + Unpin> UpdateStream<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            framer: Framer::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// A handle to the statistics of this connection. The stream records every packet it yields
+    /// as a sample.
+    pub fn stats(&self) -> LinkStats {
+        self.framer.stats().clone()
+    }
+}
+
+impl<T: AsyncRead + Unpin> Stream for UpdateStream<T> {
+    type Item = io::Result<Sample<Update>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            match this.framer.next() {
+                Some(Ok(value)) => {
+                    let host_time = Instant::now();
+                    this.framer.stats().record_sample(host_time);
+
+                    return Poll::Ready(Some(Ok(Sample {
+                        host_time,
+                        board_timestamp: None,
+                        value,
+                    })));
+                }
+                Some(Err(_)) => continue,
+                None => (),
+            }
+
+            match ready!(Pin::new(&mut this.inner).poll_read(cx, this.framer.spare())) {
+                Ok(0) => return Poll::Ready(None),
+                Ok(len) => this.framer.filled(len),
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+        }
+    }
+}
+
+impl<T> Deref for UpdateStream<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for UpdateStream<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
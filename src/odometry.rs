@@ -0,0 +1,330 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Field pose estimation from wheel odometry. The wheels only provide the distance travelled, while
+//! rotation comes from the navX since wheels slip far more than the gyro drifts.
+//!
+//! Poses follow the WPILib convention with X forward, Y to the left and the heading in degrees
+//! counterclockwise. The navX yaw increases clockwise, so it is negated to get the heading.
+//! Distances can be in any unit as long as the kinematics and wheel deltas agree.
+
+use crate::heading::ContinuousYaw;
+use crate::history::Interpolate;
+
+/// A position and heading on the field.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Pose {
+    pub x: f64,
+    pub y: f64,
+    /// Degrees counterclockwise. This is continuous, so it keeps counting past ±180.
+    pub heading: f64,
+}
+
+impl Pose {
+    pub fn new(x: f64, y: f64, heading: f64) -> Self {
+        Self { x, y, heading }
+    }
+
+    /// This pose as seen from `origin`, with `origin` at zero facing along the X axis.
+    pub fn relative_to(&self, origin: &Pose) -> Self {
+        let (sin, cos) = origin.heading.to_radians().sin_cos();
+        let (dx, dy) = (self.x - origin.x, self.y - origin.y);
+
+        Self {
+            x: dx * cos + dy * sin,
+            y: -dx * sin + dy * cos,
+            heading: self.heading - origin.heading,
+        }
+    }
+
+    /// Place a pose given relative to this one on the field. This undoes [`relative_to`].
+    ///
+    /// [`relative_to`]: Pose::relative_to
+    pub fn transform(&self, relative: &Pose) -> Self {
+        let (sin, cos) = self.heading.to_radians().sin_cos();
+
+        Self {
+            x: self.x + relative.x * cos - relative.y * sin,
+            y: self.y + relative.x * sin + relative.y * cos,
+            heading: self.heading + relative.heading,
+        }
+    }
+
+    /// Move by a displacement given relative to the robot, following a constant curvature arc
+    /// over which the heading changes by `dheading` degrees.
+    pub fn exp(&self, twist: Twist, dheading: f64) -> Self {
+        let dtheta = dheading.to_radians();
+
+        // sin(θ)/θ and (1 - cos(θ))/θ, using their series for small angles to avoid dividing by zero
+        let (s, c) = if dtheta.abs() < 1e-9 {
+            (1.0 - dtheta * dtheta / 6.0, dtheta / 2.0)
+        } else {
+            (dtheta.sin() / dtheta, (1.0 - dtheta.cos()) / dtheta)
+        };
+
+        let dx = twist.dx * s - twist.dy * c;
+        let dy = twist.dx * c + twist.dy * s;
+        let (sin, cos) = self.heading.to_radians().sin_cos();
+
+        Self {
+            x: self.x + dx * cos - dy * sin,
+            y: self.y + dx * sin + dy * cos,
+            heading: self.heading + dheading,
+        }
+    }
+}
+
+impl Interpolate for Pose {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        Self {
+            x: self.x.interpolate(&other.x, t),
+            y: self.y.interpolate(&other.y, t),
+            heading: self.heading.interpolate(&other.heading, t),
+        }
+    }
+}
+
+/// A displacement relative to the robot as measured by the wheels.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Twist {
+    /// Distance forward
+    pub dx: f64,
+    /// Distance to the left
+    pub dy: f64,
+    /// The change of heading seen by the wheels in degrees counterclockwise. Odometry uses the navX
+    /// instead, but this is useful for checking the kinematics or detecting wheel slip.
+    pub dheading: f64,
+}
+
+/// Converts the distances travelled by each wheel into a displacement of the robot.
+pub trait Kinematics {
+    /// The distances travelled by the wheels since the last update
+    type Deltas: ?Sized;
+
+    fn twist(&self, deltas: &Self::Deltas) -> Twist;
+}
+
+/// A tank drive with one set of wheels on each side.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DifferentialDrive {
+    /// The distance between the left and right wheels
+    pub track_width: f64,
+}
+
+/// The distances travelled by each side of a differential drive.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct DifferentialDeltas {
+    pub left: f64,
+    pub right: f64,
+}
+
+impl DifferentialDrive {
+    pub fn new(track_width: f64) -> Self {
+        Self { track_width }
+    }
+}
+
+impl Kinematics for DifferentialDrive {
+    type Deltas = DifferentialDeltas;
+
+    fn twist(&self, deltas: &DifferentialDeltas) -> Twist {
+        Twist {
+            dx: (deltas.left + deltas.right) / 2.0,
+            dy: 0.0,
+            dheading: ((deltas.right - deltas.left) / self.track_width).to_degrees(),
+        }
+    }
+}
+
+/// A mecanum drive with the rollers of each wheel forming an X when viewed from above.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MecanumDrive {
+    /// The distance between the front and rear wheels
+    pub wheelbase: f64,
+    /// The distance between the left and right wheels
+    pub track_width: f64,
+}
+
+/// The distances travelled by each wheel of a mecanum drive.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MecanumDeltas {
+    pub front_left: f64,
+    pub front_right: f64,
+    pub rear_left: f64,
+    pub rear_right: f64,
+}
+
+impl MecanumDrive {
+    pub fn new(wheelbase: f64, track_width: f64) -> Self {
+        Self {
+            wheelbase,
+            track_width,
+        }
+    }
+}
+
+impl Kinematics for MecanumDrive {
+    type Deltas = MecanumDeltas;
+
+    fn twist(&self, deltas: &MecanumDeltas) -> Twist {
+        let MecanumDeltas {
+            front_left,
+            front_right,
+            rear_left,
+            rear_right,
+        } = *deltas;
+        let lever = (self.wheelbase + self.track_width) / 2.0;
+
+        Twist {
+            dx: (front_left + front_right + rear_left + rear_right) / 4.0,
+            dy: (-front_left + front_right + rear_left - rear_right) / 4.0,
+            dheading: ((-front_left + front_right - rear_left + rear_right) / (4.0 * lever))
+                .to_degrees(),
+        }
+    }
+}
+
+/// A swerve drive with any number of independently steered modules.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwerveDrive {
+    modules: Vec<(f64, f64)>,
+}
+
+/// The movement of a single swerve module.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SwerveDelta {
+    /// The distance travelled by the wheel
+    pub distance: f64,
+    /// The direction the wheel is pointing in degrees counterclockwise from forward
+    pub angle: f64,
+}
+
+impl SwerveDelta {
+    pub fn new(distance: f64, angle: f64) -> Self {
+        Self { distance, angle }
+    }
+}
+
+impl SwerveDrive {
+    /// Create a swerve drive from the (x, y) position of each module relative to the center of the
+    /// robot. Deltas must be given in the same order.
+    pub fn new(modules: &[(f64, f64)]) -> Self {
+        Self {
+            modules: modules.to_vec(),
+        }
+    }
+
+    pub fn modules(&self) -> &[(f64, f64)] {
+        &self.modules
+    }
+}
+
+impl Kinematics for SwerveDrive {
+    type Deltas = [SwerveDelta];
+
+    /// Find the displacement that best fits the movement of every module in a least squares sense.
+    ///
+    /// # Panics
+    /// If the number of deltas does not match the number of modules.
+    fn twist(&self, deltas: &[SwerveDelta]) -> Twist {
+        assert_eq!(
+            deltas.len(),
+            self.modules.len(),
+            "Expected a delta for each swerve module"
+        );
+
+        let n = self.modules.len() as f64;
+        let moves: Vec<(f64, f64)> = deltas
+            .iter()
+            .map(|x| {
+                let (sin, cos) = x.angle.to_radians().sin_cos();
+                (x.distance * cos, x.distance * sin)
+            })
+            .collect();
+
+        let center_x = self.modules.iter().map(|x| x.0).sum::<f64>() / n;
+        let center_y = self.modules.iter().map(|x| x.1).sum::<f64>() / n;
+        let mean_dx = moves.iter().map(|x| x.0).sum::<f64>() / n;
+        let mean_dy = moves.iter().map(|x| x.1).sum::<f64>() / n;
+
+        // Each module moves by (dx - dθ y, dy + dθ x), so the rotation is found from how the
+        // movement of each module differs from the mean around the center of the modules
+        let mut moment = 0.0;
+        let mut inertia = 0.0;
+        for (&(x, y), &(dx, dy)) in self.modules.iter().zip(&moves) {
+            let (x, y) = (x - center_x, y - center_y);
+            moment += x * (dy - mean_dy) - y * (dx - mean_dx);
+            inertia += x * x + y * y;
+        }
+
+        let dtheta = if inertia > 0.0 { moment / inertia } else { 0.0 };
+
+        Twist {
+            dx: mean_dx + dtheta * center_y,
+            dy: mean_dy - dtheta * center_x,
+            dheading: dtheta.to_degrees(),
+        }
+    }
+}
+
+/// Tracks the pose of the robot on the field. Feed it the continuous navX angle, such as the one
+/// read from a [`YawTracker`], along with the wheel distances travelled since the previous update.
+///
+/// [`YawTracker`]: crate::heading::YawTracker
+pub struct Odometry<K> {
+    kinematics: K,
+    pose: Pose,
+    /// The heading of the pose when the navX angle is zero
+    heading_offset: f64,
+    last_angle: f64,
+}
+
+impl<K: Kinematics> Odometry<K> {
+    /// Start tracking from `pose`, with `yaw` being the current navX angle.
+    pub fn new(kinematics: K, yaw: ContinuousYaw, pose: Pose) -> Self {
+        Self {
+            kinematics,
+            pose,
+            heading_offset: pose.heading + yaw.angle,
+            last_angle: yaw.angle,
+        }
+    }
+
+    /// Move the robot to `pose`. The heading of the pose replaces the heading from the navX, so
+    /// the navX angle does not need to be reset as well.
+    pub fn reset(&mut self, yaw: ContinuousYaw, pose: Pose) {
+        self.pose = pose;
+        self.heading_offset = pose.heading + yaw.angle;
+        self.last_angle = yaw.angle;
+    }
+
+    /// Add the distances travelled by the wheels since the last update. The heading change is
+    /// taken from the navX and the path between updates is assumed to be an arc.
+    pub fn update(&mut self, yaw: ContinuousYaw, deltas: &K::Deltas) -> Pose {
+        let twist = self.kinematics.twist(deltas);
+        let dheading = self.last_angle - yaw.angle;
+
+        self.pose = self.pose.exp(twist, dheading);
+        // Set the heading directly so rounding errors from the deltas do not add up
+        self.pose.heading = self.heading_offset - yaw.angle;
+        self.last_angle = yaw.angle;
+
+        self.pose
+    }
+
+    pub fn pose(&self) -> Pose {
+        self.pose
+    }
+
+    pub fn kinematics(&self) -> &K {
+        &self.kinematics
+    }
+
+    pub fn kinematics_mut(&mut self) -> &mut K {
+        &mut self.kinematics
+    }
+}
@@ -0,0 +1,162 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Angular velocity. Rates are read straight from the gyro when raw data is available. Otherwise
+//! they can be estimated by differentiating yaw samples.
+
+use std::io;
+
+use crate::heading::YawSource;
+use crate::math::Quaternion;
+use crate::register::storage::{Config, RawGyro};
+use crate::serde::{scale_raw, Vector};
+use crate::serial::storage::RawDataUpdate;
+use crate::watch::{Sample, Watch, Watched};
+use crate::Request;
+
+/// Convert a raw gyro reading to degrees per second.
+pub fn scale_gyro(raw: Vector<i16>, gyro_fsr: u16) -> Vector<f32> {
+    raw.map(|x| scale_raw(x, f32::from(gyro_fsr)))
+}
+
+/// Angular velocity in degrees per second.
+#[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
+pub struct AngularRate {
+    /// Rotation rate about each axis of the board
+    pub body: Vector<f32>,
+    /// Rotation rate about the vertical axis of the world, positive clockwise like yaw. Unlike the
+    /// body z rate, this stays correct when the board is tilted.
+    pub yaw_rate: f32,
+}
+
+impl AngularRate {
+    /// Combine body rates with the orientation of the board at the time they were measured.
+    pub fn new(body: Vector<f32>, orientation: &Quaternion) -> Self {
+        Self {
+            body,
+            // The gyro is right handed, so a clockwise turn is negative about the up axis
+            yaw_rate: -orientation.rotate(body).z,
+        }
+    }
+
+    /// Scale a raw gyro reading by the gyro full scale range (degrees/sec).
+    pub fn from_raw(raw: Vector<i16>, gyro_fsr: u16, orientation: &Quaternion) -> Self {
+        Self::new(scale_gyro(raw, gyro_fsr), orientation)
+    }
+
+    /// Rates from a serial raw data update. The full scale range is reported in the stream
+    /// configuration response.
+    pub fn from_raw_data(update: &RawDataUpdate, gyro_fsr: u16, orientation: &Quaternion) -> Self {
+        Self::from_raw(update.gyro, gyro_fsr, orientation)
+    }
+}
+
+/// Reads angular rates over the register protocol. The gyro full scale range is read once and
+/// then cached until the connection is recovered. Every read takes two transactions, one for the
+/// raw gyro and one for the orientation.
+pub struct RateReader<S> {
+    inner: S,
+    gyro_fsr: Option<u16>,
+}
+
+impl<S> RateReader<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            gyro_fsr: None,
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> Request<AngularRate> for RateReader<S>
+where
+    S: Request<Config> + Request<RawGyro> + Request<Quaternion>,
+{
+    fn read(&mut self) -> io::Result<AngularRate> {
+        let gyro_fsr = match self.gyro_fsr {
+            Some(x) => x,
+            None => {
+                let config: Config = self.inner.read()?;
+                self.gyro_fsr = Some(config.gyro_fsr);
+                config.gyro_fsr
+            }
+        };
+
+        let raw: RawGyro = self.inner.read()?;
+        let orientation: Quaternion = self.inner.read()?;
+
+        Ok(AngularRate::from_raw(raw.gyro, gyro_fsr, &orientation))
+    }
+
+    /// The full scale range may have changed if the board was reset, so it is read again.
+    fn recover(&mut self) -> io::Result<()> {
+        self.gyro_fsr = None;
+        Request::<Config>::recover(&mut self.inner)
+    }
+
+    fn update_rate(&mut self) -> Option<u32> {
+        Request::<Config>::update_rate(&mut self.inner)
+    }
+}
+
+impl<S> Watch<AngularRate> for RateReader<S>
+where
+    S: 'static + Request<Config> + Request<RawGyro> + Request<Quaternion> + Send,
+{
+    type Provider = Self;
+
+    fn watch(self) -> Watched<AngularRate, Self::Provider> {
+        Watched::new(self)
+    }
+}
+
+/// Estimates the yaw rate (degrees/sec) from consecutive yaw samples when raw gyro data is not
+/// available. Wraparound at ±180 is handled, so samples need to be fed faster than the robot can
+/// turn half a rotation.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct YawDifferentiator {
+    last: Option<Sample<f32>>,
+    rate: Option<f32>,
+}
+
+impl YawDifferentiator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a new sample and get the current rate estimate. Samples that share a timestamp with
+    /// the previous one are ignored.
+    pub fn update<T: YawSource>(&mut self, sample: &Sample<T>) -> Option<f32> {
+        let sample = sample.with_value(sample.value.yaw_degrees());
+
+        if let Some(last) = self.last {
+            let dt = sample.seconds_since(&last);
+            if dt <= 0.0 {
+                return self.rate;
+            }
+
+            let delta = (sample.value - last.value + 180.0).rem_euclid(360.0) - 180.0;
+            self.rate = Some((f64::from(delta) / dt) as f32);
+        }
+
+        self.last = Some(sample);
+        self.rate
+    }
+
+    /// The most recent estimate, or `None` until two samples have been seen
+    pub fn rate(&self) -> Option<f32> {
+        self.rate
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
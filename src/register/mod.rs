@@ -1,9 +1,11 @@
 //! A module for dealing with the register protocol of the navX.
 use std::io::{self, ErrorKind, Read, Write};
 use std::ops::{Deref, DerefMut};
+use std::time::Instant;
 
-use crate::register::storage::Addressable;
-use crate::watch::{Watch, Watched};
+use crate::register::storage::{Addressable, Config, Identity, NAVX_IDENTITY};
+use crate::stats::LinkStats;
+use crate::watch::{Builder, Watch, Watched};
 use crate::{get_crc, Packet, Request};
 
 pub mod packet;
@@ -12,6 +14,7 @@ pub mod storage;
 pub struct RegisterIO<T> {
     inner: T,
     buffer: [u8; 0x100],
+    stats: LinkStats,
 }
 
 impl<T: Write> RegisterIO<T> {
@@ -19,14 +22,25 @@ impl<T: Write> RegisterIO<T> {
         Self {
             inner,
             buffer: [0; 0x100],
+            stats: LinkStats::new(),
         }
     }
 
+    /// A handle to the statistics of this connection
+    pub fn stats(&self) -> LinkStats {
+        self.stats.clone()
+    }
+
     /// Write a packet
     fn write<P: Packet>(&mut self, packet: P) -> io::Result<()> {
         let expected_len = packet.len();
 
-        match self.inner.write(packet.pack_write()) {
+        let written = self.inner.write(packet.pack_write());
+        if let Ok(len) = written {
+            self.stats.record_sent(len);
+        }
+
+        match written {
             Ok(x) if x == expected_len => Ok(()),
             Ok(_) => Err(ErrorKind::Interrupted.into()),
             Err(e) => Err(e),
@@ -37,22 +51,52 @@ impl<T: Write> RegisterIO<T> {
 // TODO: Will this approach to reading also work for serial inputs
 impl<T: Read + Write, V: Addressable> Request<V> for RegisterIO<T> {
     fn read(&mut self) -> io::Result<V> {
+        let start = Instant::now();
         self.write(V::request())?;
 
         // Read bytes
-        if self.inner.read(&mut self.buffer[..=V::LEN])? != (V::LEN + 1) as usize {
+        let len = self.inner.read(&mut self.buffer[..=V::LEN])?;
+        self.stats.record_transaction();
+        self.stats.record_latency(start.elapsed());
+        self.stats.record_received(len);
+
+        if len != (V::LEN + 1) as usize {
+            self.stats.record_short_read();
             return Err(ErrorKind::Interrupted.into());
         }
 
         // Checksum
         if get_crc(&self.buffer[..V::LEN], V::LEN) != self.buffer[V::LEN] {
+            self.stats.record_crc_failure();
             return Err(ErrorKind::InvalidData.into());
         }
 
         // Parse bytes to type
         match V::try_read(&self.buffer[..V::LEN]) {
             Some(x) => Ok(x),
-            None => Err(ErrorKind::InvalidData.into()),
+            None => {
+                self.stats.record_decode_failure();
+                Err(ErrorKind::InvalidData.into())
+            }
+        }
+    }
+
+    /// The rate the board is configured for, or `None` if it can not be read.
+    fn update_rate(&mut self) -> Option<u32> {
+        match Request::<Config>::read(self) {
+            Ok(Config { update_rate, .. }) if update_rate > 0 => Some(u32::from(update_rate)),
+            _ => None,
+        }
+    }
+
+    /// Make sure a navX is still on the other end of the bus
+    fn recover(&mut self) -> io::Result<()> {
+        match Request::<Identity>::read(self)? {
+            Identity { identity, .. } if identity == NAVX_IDENTITY => Ok(()),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Board did not identify as a navX",
+            )),
         }
     }
 }
@@ -60,9 +104,10 @@ impl<T: Read + Write, V: Addressable> Request<V> for RegisterIO<T> {
 impl<T: 'static + Read + Write + Send, V: 'static + Addressable> Watch<V> for RegisterIO<T> {
     type Provider = Self;
 
-    // No additional setup required
+    /// Poll at the update rate the board is configured for, recording into the statistics of
+    /// this connection.
     fn watch(self) -> Watched<V, Self::Provider> {
-        Watched::new(self)
+        Builder::new().stats(self.stats()).watch(self)
     }
 }
 
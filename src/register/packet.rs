@@ -31,6 +31,12 @@ impl RegisterPacket {
         }
     }
 
+    /// The bytes of a read request for this packet, checksum included.
+    pub fn to_bytes(&self) -> [u8; 3] {
+        let header = [self.register, self.value];
+        [header[0], header[1], get_crc(&header, 2)]
+    }
+
     fn apply_checksum(&mut self) {
         unsafe {
             let ptr = &self as *const _ as *const u8;
@@ -7,10 +7,11 @@
 
 //! All addressable areas of interest in the navX register.
 
+pub use crate::math::Quaternion;
 use crate::register::packet::RegisterPacket;
 use crate::serde::{
-    read_radians, read_u16, CalibrationStatus, Capability, OperationStatus, SelfTestStatus,
-    SensorStatus,
+    read_hundredth, read_i16, read_q214, read_thousandth, read_u16, read_u32, read_uhundredth,
+    CalibrationStatus, Capability, OperationStatus, SelfTestStatus, SensorStatus, Vector,
 };
 use crate::{FromBuffer, FromBufferFallible};
 
@@ -23,6 +24,9 @@ pub trait Addressable: FromBufferFallible + Send {
     }
 }
 
+/// The value of [`Identity::identity`] reported by every navX
+pub const NAVX_IDENTITY: u8 = 0x32;
+
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
 pub struct Identity {
     pub identity: u8,
@@ -47,32 +51,24 @@ impl FromBuffer for Identity {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
-pub struct Quaternion {
-    pub w: f32,
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
-}
-
 impl Addressable for Quaternion {
     const ADDRESS: u8 = 0x2A;
-    const LEN: usize = 4;
+    const LEN: usize = 8;
 }
 
 impl FromBuffer for Quaternion {
     fn read(buf: &[u8]) -> Self {
         Self {
-            w: read_radians(&buf[0..2]),
-            x: read_radians(&buf[2..4]),
-            y: read_radians(&buf[4..6]),
-            z: read_radians(&buf[6..8]),
+            w: read_q214(&buf[0..2]),
+            x: read_q214(&buf[2..4]),
+            y: read_q214(&buf[4..6]),
+            z: read_q214(&buf[6..8]),
         }
     }
 }
 
 /// Configuration and limits of board. The units for the following fields are update_rate (Hz),
-/// accel_fsr (Degrees/sec), and gyro_fsr (G).
+/// accel_fsr (G), and gyro_fsr (Degrees/sec).
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
 pub struct Config {
     pub update_rate: u8,
@@ -110,14 +106,85 @@ impl Addressable for Status {
 }
 
 impl FromBufferFallible for Status {
-    // FIXME: These are not the correct register locations to read
+    /// The capability flags are only read from their low byte at 0x0B, and the sensor status from
+    /// its low byte at 0x10 after the unused registers.
     fn try_read(buf: &[u8]) -> Option<Self> {
         Some(Self {
             operation_status: OperationStatus::try_read(&buf[0..1])?,
             calibration_status: CalibrationStatus::read(&buf[1..2]),
             self_test_status: SelfTestStatus::read(&buf[2..3]),
             capabilities: Capability::read(&buf[3..4]),
-            sensor_status: SensorStatus::read(&buf[4..9]),
+            sensor_status: SensorStatus::read(&buf[8..9]),
         })
     }
 }
+
+/// The raw gyro reading in the body frame. Divide by 32768 and multiply by `Config::gyro_fsr` to
+/// get degrees per second.
+#[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
+pub struct RawGyro {
+    pub gyro: Vector<i16>,
+}
+
+impl Addressable for RawGyro {
+    const ADDRESS: u8 = 0x34;
+    const LEN: usize = 6;
+}
+
+impl FromBuffer for RawGyro {
+    fn read(buf: &[u8]) -> Self {
+        Self {
+            gyro: Vector::read(read_i16, buf),
+        }
+    }
+}
+
+/// Acceleration in the world frame (G) with gravity removed. X and Y are in the horizontal plane
+/// and Z is up.
+#[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
+pub struct LinearAccel {
+    pub accel: Vector<f32>,
+}
+
+impl Addressable for LinearAccel {
+    const ADDRESS: u8 = 0x24;
+    const LEN: usize = 6;
+}
+
+impl FromBuffer for LinearAccel {
+    fn read(buf: &[u8]) -> Self {
+        Self {
+            accel: Vector::read(read_thousandth, buf),
+        }
+    }
+}
+
+/// The board timestamp followed by the fused orientation. Angles are in degrees with yaw, pitch and
+/// roll in [-180, 180) and both headings in [0, 360). The timestamp is in milliseconds.
+#[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
+pub struct Orientation {
+    pub timestamp: u32,
+    pub yaw: f32,
+    pub roll: f32,
+    pub pitch: f32,
+    pub compass_heading: f32,
+    pub fused_heading: f32,
+}
+
+impl Addressable for Orientation {
+    const ADDRESS: u8 = 0x12;
+    const LEN: usize = 14;
+}
+
+impl FromBuffer for Orientation {
+    fn read(buf: &[u8]) -> Self {
+        Self {
+            timestamp: read_u32(&buf[0..4]),
+            yaw: read_hundredth(&buf[4..6]),
+            roll: read_hundredth(&buf[6..8]),
+            pitch: read_hundredth(&buf[8..10]),
+            compass_heading: read_uhundredth(&buf[10..12]),
+            fused_heading: read_uhundredth(&buf[12..14]),
+        }
+    }
+}
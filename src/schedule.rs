@@ -0,0 +1,248 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Poll many values from a single transport on a single thread. Where a [`Watched`] value owns its
+//! transport, a [`Scheduler`] shares one transport between any number of values, each polled at its
+//! own rate.
+//!
+//! [`Watched`]: crate::watch::Watched
+
+use std::io::{self, ErrorKind};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::thread::{sleep_until, ThreadConfig};
+use crate::watch::{Sample, Slot, DEFAULT_UPDATE_RATE};
+use crate::Request;
+
+/// The longest the scheduler thread will sleep before checking if it should stop.
+const MAX_IDLE: Duration = Duration::from_millis(100);
+
+/// How often a scheduled value should be read.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Rate {
+    /// Read the value this many times per second
+    Hz(u32),
+    /// Read the value with a fixed period between the start of each read
+    Period(Duration),
+    /// Read the value a single time. Useful for values which never change such as the identity.
+    Once,
+}
+
+impl Rate {
+    fn period(self) -> Option<Duration> {
+        match self {
+            Rate::Hz(hz) => Some(Duration::from_secs(1) / hz.max(1)),
+            Rate::Period(period) => Some(period),
+            Rate::Once => None,
+        }
+    }
+}
+
+/// The outcome of a single scheduled read
+enum Poll {
+    Updated,
+    Retry,
+    Failed,
+}
+
+struct Entry<S> {
+    rate: Rate,
+    next: Instant,
+    done: bool,
+    poll: Box<dyn FnMut(&mut S) -> Poll + Send>,
+}
+
+impl<S> Entry<S> {
+    fn run(&mut self, transport: &mut S, now: Instant) {
+        match ((self.poll)(transport), self.rate.period()) {
+            (Poll::Failed, _) | (Poll::Updated, None) => self.done = true,
+            // Keep retrying until a read of a one time value goes through
+            (Poll::Retry, None) => {
+                self.next = now + Duration::from_secs(1) / u32::from(DEFAULT_UPDATE_RATE)
+            }
+            (_, Some(period)) => {
+                // Fixed rate scheduling. If the deadline was missed skip ahead instead of bursting.
+                self.next += period;
+                if self.next < now {
+                    self.next = now;
+                }
+            }
+        }
+    }
+}
+
+/// Collects the values to poll before handing the transport off to a background thread.
+pub struct Scheduler<S> {
+    transport: S,
+    entries: Vec<Entry<S>>,
+    thread: ThreadConfig,
+}
+
+impl<S: 'static + Send> Scheduler<S> {
+    pub fn new(transport: S) -> Self {
+        Self {
+            transport,
+            entries: Vec::new(),
+            thread: ThreadConfig::new(),
+        }
+    }
+
+    /// Configure the polling thread. By default it is named `navx-scheduler`.
+    pub fn thread(mut self, config: ThreadConfig) -> Self {
+        self.thread = config;
+        self
+    }
+
+    /// Add a value to be polled at the given rate. The returned handle can be used to read the
+    /// latest value or subscribe to every sample once the scheduler has been started. In the same
+    /// way as [`Watched`], a fatal read error stops this value from being polled and is stored in
+    /// its place. Other values continue to be polled.
+    ///
+    /// [`Watched`]: crate::watch::Watched
+    pub fn add<T>(&mut self, rate: Rate) -> Polled<T>
+    where
+        S: Request<T>,
+        T: 'static + Send + Sync,
+    {
+        let slot = Arc::new(Slot::new());
+        let writer = slot.clone();
+
+        self.entries.push(Entry {
+            rate,
+            next: Instant::now(),
+            done: false,
+            poll: Box::new(move |transport| {
+                let host_time = Instant::now();
+
+                match transport.read() {
+                    Ok(value) => {
+                        writer.update(Sample {
+                            host_time,
+                            board_timestamp: None,
+                            value,
+                        });
+                        Poll::Updated
+                    }
+                    Err(ref e) if e.kind() == ErrorKind::Interrupted => Poll::Retry,
+                    x => {
+                        writer.store(x);
+                        Poll::Failed
+                    }
+                }
+            }),
+        });
+
+        Polled { slot }
+    }
+
+    /// Start polling all of the added values on a single background thread.
+    pub fn start(self) -> Scheduled<S> {
+        let stop_indicator = Arc::new(AtomicBool::new(false));
+        let stop = stop_indicator.clone();
+
+        let Scheduler {
+            mut transport,
+            mut entries,
+            thread,
+        } = self;
+
+        let join_handle = thread.spawn("navx-scheduler", move || {
+            while !stop.load(Ordering::SeqCst) {
+                let now = Instant::now();
+
+                for entry in entries.iter_mut() {
+                    if !entry.done && entry.next <= now {
+                        entry.run(&mut transport, now);
+                    }
+                }
+
+                let wake = entries
+                    .iter()
+                    .filter(|entry| !entry.done)
+                    .map(|entry| entry.next)
+                    .min()
+                    .unwrap_or_else(|| now + MAX_IDLE)
+                    .min(now + MAX_IDLE);
+
+                sleep_until(wake, || stop.load(Ordering::SeqCst));
+            }
+
+            transport
+        });
+
+        Scheduled {
+            stop_indicator,
+            join_handle: Some(join_handle),
+        }
+    }
+}
+
+/// A running scheduler. The transport can be retrieved again by stopping it. Dropping it stops the
+/// scheduler thread and waits for it to finish.
+pub struct Scheduled<S> {
+    stop_indicator: Arc<AtomicBool>,
+    join_handle: Option<JoinHandle<S>>,
+}
+
+impl<S> Scheduled<S> {
+    /// Stop polling and return the transport once the scheduler thread has finished.
+    pub fn stop(mut self) -> io::Result<S> {
+        match self.join() {
+            Some(Ok(transport)) => Ok(transport),
+            _ => Err(io::Error::new(ErrorKind::BrokenPipe, "")),
+        }
+    }
+
+    /// Signal the scheduler thread to stop and wait for it.
+    fn join(&mut self) -> Option<thread::Result<S>> {
+        self.stop_indicator.store(true, Ordering::SeqCst);
+
+        let handle = self.join_handle.take()?;
+        if handle.thread().id() == thread::current().id() {
+            return None;
+        }
+
+        handle.thread().unpark();
+        Some(handle.join())
+    }
+
+    /// Checks if the scheduler thread has been asked to stop
+    pub fn is_stopped(&self) -> bool {
+        self.stop_indicator.load(Ordering::SeqCst)
+    }
+}
+
+impl<S> Drop for Scheduled<S> {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+/// A handle to a value polled by a [`Scheduler`].
+pub struct Polled<T> {
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> Clone for Polled<T> {
+    fn clone(&self) -> Self {
+        Self {
+            slot: self.slot.clone(),
+        }
+    }
+}
+
+impl<T> Deref for Polled<T> {
+    type Target = Slot<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.slot
+    }
+}
@@ -67,6 +67,11 @@ pub fn read_radians(buf: &[u8]) -> f32 {
     f32::from(LittleEndian::read_i16(buf)) * PI / 16384.0
 }
 
+/// Signed Q2.14 fixed point, used for the quaternion components
+pub fn read_q214(buf: &[u8]) -> f32 {
+    f32::from(LittleEndian::read_i16(buf)) / 16384.0
+}
+
 pub fn read_q1616(buf: &[u8]) -> f64 {
     f64::from(LittleEndian::read_u32(buf)) / 66536.0
 }
@@ -75,10 +80,18 @@ pub fn read_u32(buf: &[u8]) -> u32 {
     LittleEndian::read_u32(buf)
 }
 
+/// Raw sensor readings are signed 16 bit values spanning the full scale range
+const RAW_FULL_SCALE: f32 = 32768.0;
+
+/// Convert a raw sensor reading to the units of its full scale range
+pub fn scale_raw(raw: i16, fsr: f32) -> f32 {
+    f32::from(raw) / RAW_FULL_SCALE * fsr
+}
+
 /// ASCII float (idk why they thought this was a good idea)
 /// Format: [- ][0-9][0-9][0-9].[0-9][0-9]
 pub fn read_float(buf: &[u8]) -> Option<f32> {
-    f32::from_str(std::str::from_utf8(buf).ok()?).ok()
+    f32::from_str(std::str::from_utf8(buf).ok()?.trim()).ok()
 }
 
 /// ASCII byte as hex (idk why they thought this was a good idea)
@@ -93,6 +106,12 @@ pub fn read_int(buf: &[u8]) -> Option<u16> {
     u16::from_str_radix(std::str::from_utf8(buf).ok()?, 16).ok()
 }
 
+/// ASCII signed short as hex in two's complement
+/// Format: [0-9A-F][0-9A-F][0-9A-F][0-9A-F]
+pub fn read_signed_int(buf: &[u8]) -> Option<i16> {
+    read_int(buf).map(|x| x as i16)
+}
+
 bitflags! {
     pub struct SensorStatus: u8 {
         const MOVING              = 0b0000_0001;
@@ -135,6 +154,19 @@ bitflags! {
     }
 }
 
+impl Capability {
+    /// The axis the board was configured to treat as up, or `None` if the board does not support
+    /// OmniMount.
+    pub fn omnimount_config(&self) -> Option<OmniMountConfig> {
+        if !self.contains(Capability::OMNIMOUNT) {
+            return None;
+        }
+
+        let config = (self.bits() & Capability::OMNIMOUNT_CONFIG_MASK.bits()) >> 3;
+        OmniMountConfig::try_read(&[config])
+    }
+}
+
 bitflags! {
     pub struct ControlReset: u8 {
         // Velocity
@@ -183,9 +215,11 @@ impl FromBufferFallible for OperationStatus {
     }
 }
 
+/// The axis of the board that points up, as configured with OmniMount. The default is Z up.
 #[repr(u8)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub enum OmniMountConfig {
+    #[default]
     Default = 0,
     XUp = 1,
     XDown = 2,
@@ -233,6 +267,10 @@ pub struct Vector<T> {
 }
 
 impl<T> Vector<T> {
+    pub fn new(x: T, y: T, z: T) -> Self {
+        Self { x, y, z }
+    }
+
     pub fn read(read: fn(&[u8]) -> T, buf: &[u8]) -> Self {
         let segment = buf.len() / 3;
         Self {
@@ -241,4 +279,13 @@ impl<T> Vector<T> {
             z: read(&buf[2 * segment..]),
         }
     }
+
+    /// Apply a function to each component of this vector.
+    pub fn map<U, F: Fn(T) -> U>(self, f: F) -> Vector<U> {
+        Vector {
+            x: f(self.x),
+            y: f(self.y),
+            z: f(self.z),
+        }
+    }
 }
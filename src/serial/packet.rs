@@ -1,96 +1,250 @@
-use std::io::{self, Read};
+use std::io::{self, ErrorKind, Read};
+use std::time::Instant;
 
 use memchr::memchr;
-use std::convert::TryFrom;
+
+use crate::serde::read_byte;
+use crate::serial::storage::{
+    DirectionalUpdate, PositionUpdate, RawDataUpdate, StreamConfigurationResponse,
+};
+use crate::stats::LinkStats;
+use crate::{FromBufferFallible, Request};
 
 const PACKET_INDICATOR: u8 = b'!';
+const BINARY_INDICATOR: u8 = b'#';
 
-pub struct PacketReader<T: Read> {
-    inner: T,
-    buf: [u8; 256],
+/// Every packet ends with a two character checksum followed by "\r\n"
+const TERMINATION_LEN: usize = 4;
+
+/// Large enough to always hold a few complete packets
+const BUFFER_LEN: usize = 256;
+
+/// A single decoded packet from the serial stream of the navX.
+pub enum Update {
+    Directional(DirectionalUpdate),
+    RawData(RawDataUpdate),
+    Position(PositionUpdate),
+    StreamResponse(StreamConfigurationResponse),
+}
+
+impl Update {
+    /// Decode the body of a packet. The body must be exactly the length used by the message.
+    fn decode(msg_id: u8, body: &[u8]) -> Option<Self> {
+        match (msg_id, body.len()) {
+            (b'y', 28) => DirectionalUpdate::try_read(body).map(Update::Directional),
+            (b'g', 40) => RawDataUpdate::try_read(body).map(Update::RawData),
+            (b'p', 58) => PositionUpdate::try_read(body).map(Update::Position),
+            (b's', 40) => StreamConfigurationResponse::try_read(body).map(Update::StreamResponse),
+            _ => None,
+        }
+    }
+}
+
+/// The total length of an ASCII packet. Binary packets carry their own length instead.
+fn ascii_len(msg_id: u8) -> Option<usize> {
+    match msg_id {
+        b'y' => Some(34), // Heading update
+        b'g' => Some(46), // Raw data update
+        b's' => Some(46), // Stream configuration response
+        _ => None,
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PacketParseError {
+    /// More bytes are needed to finish the packet
+    Unfinished,
+    /// The packet is not one we know how to read
+    Malformed,
+    /// The checksum did not match the contents of the packet
+    Checksum,
+}
+
+/// Parse a single packet from the start of the buffer, returning it along with its length.
+///
+/// Format: ! [msg id] [body] [checksum] \r\n
+///     or: ! # [len] [msg id] [body] [checksum] \r\n
+///
+/// The checksum is the sum of every byte before it written as two hex characters.
+fn parse(buf: &[u8]) -> Result<(usize, Update), PacketParseError> {
+    let (msg_id, body_start, len) = match buf.get(1) {
+        None => return Err(PacketParseError::Unfinished),
+        Some(&BINARY_INDICATOR) => match (buf.get(2), buf.get(3)) {
+            // The length of a binary packet does not include the first two characters
+            (Some(&len), Some(&msg_id)) => (msg_id, 4, usize::from(len) + 2),
+            _ => return Err(PacketParseError::Unfinished),
+        },
+        Some(&msg_id) => match ascii_len(msg_id) {
+            Some(len) => (msg_id, 2, len),
+            None => return Err(PacketParseError::Malformed),
+        },
+    };
+
+    if len < body_start + TERMINATION_LEN {
+        return Err(PacketParseError::Malformed);
+    }
+
+    if buf.len() < len {
+        return Err(PacketParseError::Unfinished);
+    }
+
+    let end = len - TERMINATION_LEN;
+    let checksum = buf[..end].iter().fold(0u8, |sum, &x| sum.wrapping_add(x));
+
+    if read_byte(&buf[end..end + 2]) != Some(checksum) {
+        return Err(PacketParseError::Checksum);
+    }
+
+    if &buf[end + 2..len] != b"\r\n" {
+        return Err(PacketParseError::Malformed);
+    }
+
+    match Update::decode(msg_id, &buf[body_start..end]) {
+        Some(update) => Ok((len, update)),
+        None => Err(PacketParseError::Malformed),
+    }
+}
+
+/// Splits a stream of bytes into packets. This holds no reader so the same framing can be shared
+/// between blocking and non-blocking readers.
+pub(crate) struct Framer {
+    buf: [u8; BUFFER_LEN],
     start: usize,
     pos: usize,
+    stats: LinkStats,
+    /// Set while bytes are being discarded so each resync is only counted once
+    resyncing: bool,
 }
 
-impl<T: Read> PacketReader<T> {
-    pub fn new(reader: T) -> Self {
+impl Framer {
+    pub(crate) fn new() -> Self {
         Self {
-            inner: reader,
-            buf: [0; 256],
-            pos: 0,
+            buf: [0; BUFFER_LEN],
             start: 0,
+            pos: 0,
+            stats: LinkStats::new(),
+            resyncing: false,
         }
     }
 
-    fn try_read(&mut self) -> io::Result<bool> {
-        let len = self.inner.read(&mut self.buf[self.pos..])?;
+    pub(crate) fn stats(&self) -> &LinkStats {
+        &self.stats
+    }
 
-        if len == 0 {
-            return Ok(false);
+    fn resync(&mut self) {
+        if !self.resyncing {
+            self.resyncing = true;
+            self.stats.record_resync();
         }
+    }
 
-        match memchr(PACKET_INDICATOR, &self.buf[self.pos..self.pos + len]) {
+    /// The free space at the end of the buffer to read new bytes into. Bytes which have already
+    /// been parsed are discarded first.
+    pub(crate) fn spare(&mut self) -> &mut [u8] {
+        if self.start > 0 {
+            self.buf.copy_within(self.start..self.pos, 0);
+            self.pos -= self.start;
+            self.start = 0;
+        }
+
+        &mut self.buf[self.pos..]
+    }
+
+    /// Mark `len` bytes of the spare space as filled
+    pub(crate) fn filled(&mut self, len: usize) {
+        self.pos = (self.pos + len).min(BUFFER_LEN);
+        self.stats.record_received(len);
+    }
+
+    /// Try to take the next packet out of the buffered bytes. Returns `None` once more bytes are
+    /// needed. After a bad packet, parsing resumes at the next packet indicator.
+    pub(crate) fn next(&mut self) -> Option<Result<Update, PacketParseError>> {
+        match memchr(PACKET_INDICATOR, &self.buf[self.start..self.pos]) {
+            Some(0) => (),
             Some(x) => {
+                self.resync();
                 self.start += x;
             }
-            None => (),
+            None => {
+                if self.start < self.pos {
+                    self.resync();
+                }
+                self.start = self.pos;
+                return None;
+            }
+        }
+
+        let result = match parse(&self.buf[self.start..self.pos]) {
+            Ok((len, update)) => {
+                self.start += len;
+                self.resyncing = false;
+                self.stats.record_transaction();
+                return Some(Ok(update));
+            }
+            // No packet is as long as the buffer, so being stuck with a full buffer means the
+            // indicator we are waiting on was garbage.
+            Err(PacketParseError::Unfinished) if self.start == 0 && self.pos == BUFFER_LEN => {
+                PacketParseError::Malformed
+            }
+            Err(PacketParseError::Unfinished) => return None,
+            Err(e) => e,
         };
 
-        Ok(false)
+        match result {
+            PacketParseError::Checksum => self.stats.record_crc_failure(),
+            _ => self.stats.record_decode_failure(),
+        }
+
+        self.resync();
+        self.start += 1;
+        Some(Err(result))
     }
 }
 
-#[repr(C, packed)]
-struct Packet<T> {
-    indicator: u8,
-    msg_id: u8,
-    body: T,
-    termination: PacketTermination,
+/// Reads decoded packets from a serial connection. Bad packets are skipped.
+pub struct PacketReader<T> {
+    inner: T,
+    framer: Framer,
 }
 
-impl<T> TryFrom<&[u8]> for Packet<T> {
-    type Error = PacketParseError;
-
-    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        if value[0] != PACKET_INDICATOR {
-            return Err(PacketParseError::Malformed);
+impl<T: Read> PacketReader<T> {
+    pub fn new(reader: T) -> Self {
+        Self {
+            inner: reader,
+            framer: Framer::new(),
         }
-
-        match value[1] {
-            b'y' => (), // Heading update
-            b'g' => (), // Raw data update
-            b'S' => (), // Stream configuration command
-            b's' => (), // Stream configuration response
-            b'#' => (), // Binary packet indicator
-            _ => return Err(PacketParseError::Malformed),
-        };
-
-        unimplemented!()
     }
-}
 
-enum PacketParseError {
-    Unfinished,
-    Malformed,
-}
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
 
-#[repr(C, packed)]
-struct BinaryPacket<T> {
-    len: u8,
-    msg_id: u8,
-    body: T,
+    /// A handle to the statistics of this connection
+    pub fn stats(&self) -> LinkStats {
+        self.framer.stats().clone()
+    }
 }
 
-#[repr(C, packed)]
-struct PacketTermination {
-    // crc7 checksum
-    checksum: [u8; 2],
-    // characters \r\n (unchecked, but included for correctness)
-    _break: u16,
-}
+impl<T: Read> Request<Update> for PacketReader<T> {
+    /// Block until the next complete packet has been read. A read that returns no bytes is treated
+    /// as a timeout and reported as interrupted.
+    fn read(&mut self) -> io::Result<Update> {
+        let start = Instant::now();
+
+        loop {
+            match self.framer.next() {
+                Some(Ok(update)) => {
+                    self.framer.stats.record_latency(start.elapsed());
+                    return Ok(update);
+                }
+                Some(Err(_)) => continue,
+                None => (),
+            }
 
-impl PacketTermination {
-    fn checksum(&self) -> Option<u8> {
-        u8::from_str_radix(std::str::from_utf8(&self.checksum[..]).ok()?, 16).ok()
+            match self.inner.read(self.framer.spare())? {
+                0 => return Err(ErrorKind::Interrupted.into()),
+                len => self.framer.filled(len),
+            }
+        }
     }
 }
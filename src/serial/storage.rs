@@ -1,10 +1,8 @@
+use crate::math::Quaternion;
 use crate::serde::*;
 use crate::{FromBuffer, FromBufferFallible};
 use std::convert::TryInto;
 
-// TODO: Find a shared place to put Quaternions
-use crate::register::storage::Quaternion;
-
 /// A directional yaw/pitch/roll/heading update. I decided against using a vector for the
 /// yaw/pitch/roll to preserve the clear naming.
 pub struct DirectionalUpdate {
@@ -18,8 +16,8 @@ impl FromBufferFallible for DirectionalUpdate {
     fn try_read(buf: &[u8]) -> Option<Self> {
         Some(Self {
             yaw: read_float(&buf[0..7])?,
-            pitch: read_float(&buf[7..14])?,
-            roll: read_float(&buf[14..21])?,
+            roll: read_float(&buf[7..14])?,
+            pitch: read_float(&buf[14..21])?,
             compass_heading: read_float(&buf[21..28])?,
         })
     }
@@ -41,14 +39,23 @@ pub struct RawDataUpdate {
 impl FromBufferFallible for RawDataUpdate {
     fn try_read(buf: &[u8]) -> Option<Self> {
         Some(Self {
-            gyro: Vector::read(read_i16, &buf[0..12]),
-            acceleration: Vector::read(read_i16, &buf[12..24]),
-            magnetometer: Vector::read(read_i16, &buf[24..36]),
-            temperature: read_float(&buf[36..43])?,
+            gyro: read_hex_vector(&buf[0..12])?,
+            acceleration: read_hex_vector(&buf[12..24])?,
+            magnetometer: read_hex_vector(&buf[24..36])?,
+            temperature: f32::from(read_signed_int(&buf[36..40])?) / 100.0,
         })
     }
 }
 
+/// Three ASCII signed shorts in a row
+fn read_hex_vector(buf: &[u8]) -> Option<Vector<i16>> {
+    Some(Vector::new(
+        read_signed_int(&buf[0..4])?,
+        read_signed_int(&buf[4..8])?,
+        read_signed_int(&buf[8..12])?,
+    ))
+}
+
 pub struct Status {
     pub operation: OperationStatus,
     pub sensor: SensorStatus,
@@ -86,8 +93,8 @@ impl FromBufferFallible for PositionUpdate {
     fn try_read(buf: &[u8]) -> Option<Self> {
         Some(Self {
             yaw: read_hundredth(&buf[0..2]),
-            pitch: read_hundredth(&buf[2..4]),
-            roll: read_hundredth(&buf[4..6]),
+            roll: read_hundredth(&buf[2..4]),
+            pitch: read_hundredth(&buf[4..6]),
             compass_heading: read_uhundredth(&buf[6..8]),
             altitude: read_q1616(&buf[8..12]),
             fused_heading: read_uhundredth(&buf[12..14]),
@@ -124,7 +131,7 @@ impl FromBufferFallible for StreamConfigurationResponse {
             update_rate: read_int(&buf[9..13])?,
             calibrated_yaw_offset: read_float(&buf[13..20])?,
             reserved: buf[20..36].try_into().unwrap(),
-            flags: CalibrationStatus::read(&buf[36..37]),
+            flags: CalibrationStatus::from_bits_truncate(read_int(&buf[36..40])? as u8),
         })
     }
 }
@@ -0,0 +1,278 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Counters and timing metrics for a connection to the board. Transports record every transaction
+//! as it happens, while watchers record retries and the timing of the samples they publish. Take a
+//! [`LinkSnapshot`] to print or publish the current numbers.
+
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The number of recent latencies and sample intervals timing metrics are computed over
+pub const STATS_WINDOW: usize = 1000;
+
+#[derive(Default)]
+struct Counters {
+    transactions: AtomicUsize,
+    bytes_sent: AtomicUsize,
+    bytes_received: AtomicUsize,
+    crc_failures: AtomicUsize,
+    short_reads: AtomicUsize,
+    decode_failures: AtomicUsize,
+    retries: AtomicUsize,
+    resyncs: AtomicUsize,
+    samples: AtomicUsize,
+}
+
+#[derive(Default)]
+struct Timing {
+    latencies: VecDeque<Duration>,
+    last_sample: Option<Instant>,
+    intervals: VecDeque<Duration>,
+}
+
+/// Push a value into a window, evicting the oldest value once it is full.
+fn push_window(window: &mut VecDeque<Duration>, value: Duration) {
+    if window.len() == STATS_WINDOW {
+        window.pop_front();
+    }
+    window.push_back(value);
+}
+
+/// A shared handle to the statistics of a single link. Clones refer to the same statistics, so one
+/// handle can be given to both a transport and the watcher polling it.
+#[derive(Clone, Default)]
+pub struct LinkStats {
+    counters: Arc<Counters>,
+    timing: Arc<Mutex<Timing>>,
+}
+
+impl LinkStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add(counter: &AtomicUsize, count: usize) {
+        counter.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Record a completed exchange with the board
+    pub fn record_transaction(&self) {
+        Self::add(&self.counters.transactions, 1);
+    }
+
+    /// Record how long a transaction took from start to finish
+    pub fn record_latency(&self, latency: Duration) {
+        push_window(&mut self.timing.lock().latencies, latency);
+    }
+
+    pub fn record_sent(&self, bytes: usize) {
+        Self::add(&self.counters.bytes_sent, bytes);
+    }
+
+    pub fn record_received(&self, bytes: usize) {
+        Self::add(&self.counters.bytes_received, bytes);
+    }
+
+    pub fn record_crc_failure(&self) {
+        Self::add(&self.counters.crc_failures, 1);
+    }
+
+    /// Record a read that returned fewer bytes than requested
+    pub fn record_short_read(&self) {
+        Self::add(&self.counters.short_reads, 1);
+    }
+
+    /// Record a response that passed its checksum but could not be decoded
+    pub fn record_decode_failure(&self) {
+        Self::add(&self.counters.decode_failures, 1);
+    }
+
+    /// Record an attempt to recover the connection after a failed read
+    pub fn record_retry(&self) {
+        Self::add(&self.counters.retries, 1);
+    }
+
+    /// Record bytes being discarded to find the start of the next serial packet
+    pub fn record_resync(&self) {
+        Self::add(&self.counters.resyncs, 1);
+    }
+
+    /// Record a new sample read at `time`. The sample rate and jitter are computed from these.
+    pub fn record_sample(&self, time: Instant) {
+        Self::add(&self.counters.samples, 1);
+
+        let mut timing = self.timing.lock();
+        if let Some(last) = timing.last_sample {
+            push_window(&mut timing.intervals, time.saturating_duration_since(last));
+        }
+        timing.last_sample = Some(time);
+    }
+
+    /// Clear every counter and timing window.
+    pub fn reset(&self) {
+        let counters = &self.counters;
+        for counter in [
+            &counters.transactions,
+            &counters.bytes_sent,
+            &counters.bytes_received,
+            &counters.crc_failures,
+            &counters.short_reads,
+            &counters.decode_failures,
+            &counters.retries,
+            &counters.resyncs,
+            &counters.samples,
+        ]
+        .iter()
+        {
+            counter.store(0, Ordering::Relaxed);
+        }
+
+        *self.timing.lock() = Timing::default();
+    }
+
+    /// Copy out the current statistics.
+    pub fn snapshot(&self) -> LinkSnapshot {
+        let load = |counter: &AtomicUsize| counter.load(Ordering::Relaxed);
+        let counters = &self.counters;
+        let timing = self.timing.lock();
+
+        LinkSnapshot {
+            transactions: load(&counters.transactions),
+            bytes_sent: load(&counters.bytes_sent),
+            bytes_received: load(&counters.bytes_received),
+            crc_failures: load(&counters.crc_failures),
+            short_reads: load(&counters.short_reads),
+            decode_failures: load(&counters.decode_failures),
+            retries: load(&counters.retries),
+            resyncs: load(&counters.resyncs),
+            samples: load(&counters.samples),
+            latency: Latency::from_window(&timing.latencies),
+            sample_rate: sample_rate(&timing.intervals),
+            jitter: jitter(&timing.intervals),
+        }
+    }
+}
+
+/// Transaction latency over the most recent [`STATS_WINDOW`] transactions
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Latency {
+    pub min: Duration,
+    pub mean: Duration,
+    pub max: Duration,
+    pub p99: Duration,
+}
+
+impl Latency {
+    fn from_window(window: &VecDeque<Duration>) -> Option<Self> {
+        if window.is_empty() {
+            return None;
+        }
+
+        let mut sorted: Vec<Duration> = window.iter().cloned().collect();
+        sorted.sort();
+
+        let total: Duration = sorted.iter().sum();
+        let p99 = (sorted.len() * 99 / 100).min(sorted.len() - 1);
+
+        Some(Self {
+            min: sorted[0],
+            mean: total / sorted.len() as u32,
+            max: sorted[sorted.len() - 1],
+            p99: sorted[p99],
+        })
+    }
+}
+
+/// The rate samples arrived at over the window (Hz)
+fn sample_rate(intervals: &VecDeque<Duration>) -> Option<f64> {
+    let total: Duration = intervals.iter().sum();
+
+    if total == Duration::from_secs(0) {
+        return None;
+    }
+
+    Some(intervals.len() as f64 / total.as_secs_f64())
+}
+
+/// The standard deviation of the time between samples over the window
+fn jitter(intervals: &VecDeque<Duration>) -> Option<Duration> {
+    if intervals.len() < 2 {
+        return None;
+    }
+
+    let n = intervals.len() as f64;
+    let mean = intervals.iter().map(Duration::as_secs_f64).sum::<f64>() / n;
+    let variance = intervals
+        .iter()
+        .map(|x| (x.as_secs_f64() - mean).powi(2))
+        .sum::<f64>()
+        / n;
+
+    Some(Duration::from_secs_f64(variance.sqrt()))
+}
+
+/// The statistics of a link at a single point in time.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LinkSnapshot {
+    pub transactions: usize,
+    pub bytes_sent: usize,
+    pub bytes_received: usize,
+    pub crc_failures: usize,
+    pub short_reads: usize,
+    pub decode_failures: usize,
+    pub retries: usize,
+    pub resyncs: usize,
+    pub samples: usize,
+    /// `None` until a transaction has been timed
+    pub latency: Option<Latency>,
+    /// `None` until at least two samples have been recorded
+    pub sample_rate: Option<f64>,
+    /// The standard deviation of the time between samples
+    pub jitter: Option<Duration>,
+}
+
+impl fmt::Display for LinkSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "transactions: {}, sent: {}B, received: {}B, crc failures: {}, short reads: {}, \
+             decode failures: {}, retries: {}, resyncs: {}, samples: {}",
+            self.transactions,
+            self.bytes_sent,
+            self.bytes_received,
+            self.crc_failures,
+            self.short_reads,
+            self.decode_failures,
+            self.retries,
+            self.resyncs,
+            self.samples,
+        )?;
+
+        if let Some(latency) = self.latency {
+            write!(
+                f,
+                ", latency: {:?}/{:?}/{:?} (min/mean/max), p99: {:?}",
+                latency.min, latency.mean, latency.max, latency.p99
+            )?;
+        }
+
+        if let Some(rate) = self.sample_rate {
+            write!(f, ", rate: {:.1}Hz", rate)?;
+        }
+
+        if let Some(jitter) = self.jitter {
+            write!(f, ", jitter: {:?}", jitter)?;
+        }
+
+        Ok(())
+    }
+}
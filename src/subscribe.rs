@@ -0,0 +1,195 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Broadcast every new sample to any number of channels. Unlike the cached value of a watcher, a
+//! subscription sees every sample that is read, which is what loggers and filters need.
+
+use crossbeam_channel::{
+    bounded, unbounded, Receiver, SendTimeoutError, Sender, TryRecvError, TrySendError,
+};
+use parking_lot::Mutex;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::watch::Sample;
+
+/// How often a blocked send checks whether the subscribers have been closed
+const BLOCK_CHECK: Duration = Duration::from_millis(10);
+
+/// What to do with a new sample when a subscriber's buffer is full
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Overflow {
+    /// Discard the oldest buffered sample to make room for the new one
+    DropOldest,
+    /// Discard the new sample
+    DropNewest,
+    /// Block the polling thread until the subscriber makes room. Use with care since a slow
+    /// subscriber will hold up every other consumer of the same value: the latest value of a
+    /// watcher is only updated once every subscriber has the sample. Stopping the watcher gives up
+    /// on the send.
+    Block,
+}
+
+/// The size of the channel used for a subscription
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Capacity {
+    /// Buffer up to this many samples. A capacity of zero is treated as one, since nothing could
+    /// ever be buffered otherwise.
+    Bounded(usize, Overflow),
+    Unbounded,
+}
+
+/// A receiver for every sample published after it was created. This derefs into the underlying
+/// crossbeam receiver.
+pub struct Subscription<T> {
+    receiver: Receiver<Sample<T>>,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl<T> Subscription<T> {
+    /// The number of samples that were dropped because this subscription's buffer was full
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::SeqCst)
+    }
+}
+
+impl<T> Deref for Subscription<T> {
+    type Target = Receiver<Sample<T>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.receiver
+    }
+}
+
+/// Sends a sample to a single subscriber. Returns false once the subscriber has hung up.
+pub(crate) type Publish<T> = Box<dyn Fn(&Sample<T>) -> bool + Send>;
+
+/// The set of channels samples are broadcast to.
+pub struct Subscribers<T> {
+    list: Mutex<Vec<Publish<T>>>,
+    /// Set once no more samples will be published, so blocked sends can give up
+    closed: Arc<AtomicBool>,
+}
+
+impl<T> Default for Subscribers<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Subscribers<T> {
+    pub fn new() -> Self {
+        Self {
+            list: Mutex::new(Vec::new()),
+            closed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Add a raw listener which is called with every sample until it returns false.
+    pub(crate) fn listen(&self, publish: Publish<T>) {
+        self.list.lock().push(publish);
+    }
+
+    /// Send a sample to every subscriber. Subscribers which have been dropped are removed.
+    pub fn publish(&self, sample: &Sample<T>) {
+        self.list.lock().retain(|publish| publish(sample));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.lock().is_empty()
+    }
+
+    /// Give up on any send blocked on a full subscriber, and on every later one. This can be
+    /// called from any thread while another is publishing.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+}
+
+impl<T: 'static + Clone + Send> Subscribers<T> {
+    pub fn subscribe(&self, capacity: Capacity) -> Subscription<T> {
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let counter = dropped.clone();
+
+        let (sender, receiver) = match capacity {
+            Capacity::Bounded(size, _) => bounded(size.max(1)),
+            Capacity::Unbounded => unbounded(),
+        };
+
+        let publish: Publish<T> = match capacity {
+            Capacity::Unbounded => Box::new(move |sample| sender.send(sample.clone()).is_ok()),
+            Capacity::Bounded(_, Overflow::Block) => {
+                let closed = self.closed.clone();
+                Box::new(move |sample| send_blocking(&sender, sample.clone(), &closed))
+            }
+            Capacity::Bounded(_, Overflow::DropNewest) => {
+                Box::new(move |sample| match sender.try_send(sample.clone()) {
+                    Err(TrySendError::Full(_)) => {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        true
+                    }
+                    x => x.is_ok(),
+                })
+            }
+            Capacity::Bounded(_, Overflow::DropOldest) => {
+                // Holding onto a receiver means the channel never disconnects, so check if the
+                // subscription is still alive through the shared counter instead.
+                let oldest = receiver.clone();
+                Box::new(move |sample| {
+                    Arc::strong_count(&counter) > 1
+                        && drop_oldest(&sender, &oldest, sample.clone(), &counter)
+                })
+            }
+        };
+
+        self.list.lock().push(publish);
+
+        Subscription { receiver, dropped }
+    }
+}
+
+/// Send a sample, waiting for room until the subscribers are closed.
+fn send_blocking<T>(sender: &Sender<T>, mut sample: T, closed: &AtomicBool) -> bool {
+    loop {
+        if closed.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        match sender.send_timeout(sample, BLOCK_CHECK) {
+            Ok(()) => return true,
+            Err(SendTimeoutError::Timeout(x)) => sample = x,
+            Err(SendTimeoutError::Disconnected(_)) => return false,
+        }
+    }
+}
+
+/// Send a sample, making room by removing samples from the front of the channel if needed.
+fn drop_oldest<T>(
+    sender: &Sender<T>,
+    oldest: &Receiver<T>,
+    mut sample: T,
+    dropped: &AtomicUsize,
+) -> bool {
+    loop {
+        match sender.try_send(sample) {
+            Ok(()) => return true,
+            Err(TrySendError::Disconnected(_)) => return false,
+            Err(TrySendError::Full(x)) => sample = x,
+        }
+
+        match oldest.try_recv() {
+            Ok(_) => {
+                dropped.fetch_add(1, Ordering::SeqCst);
+            }
+            // The subscriber emptied the channel in the meantime, so just try again
+            Err(TryRecvError::Empty) => (),
+            Err(TryRecvError::Disconnected) => return false,
+        }
+    }
+}
@@ -0,0 +1,173 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Options for the background threads that poll the board. On the roboRIO, giving the polling
+//! thread a real-time priority keeps it from being preempted by logging or dashboard threads.
+
+use std::io;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// The number of CPUs that fit in a CPU set. Setting a CPU past this panics.
+#[cfg(target_os = "linux")]
+const MAX_CPUS: usize = libc::CPU_SETSIZE as usize;
+#[cfg(not(target_os = "linux"))]
+const MAX_CPUS: usize = usize::MAX;
+
+/// How to set up a polling thread. Real-time options are applied from inside the new thread, and a
+/// failure to apply them is logged instead of stopping the thread. Setting a real-time priority
+/// usually requires root or `CAP_SYS_NICE`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ThreadConfig {
+    name: Option<String>,
+    priority: Option<i32>,
+    cpus: Vec<usize>,
+}
+
+impl ThreadConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name the thread so it can be told apart in debuggers and `top`. Linux only shows the first
+    /// 15 bytes.
+    pub fn name<N: Into<String>>(mut self, name: N) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Run the thread under the `SCHED_FIFO` policy with the given priority (1 to 99, higher
+    /// preempts lower). Only supported on Linux.
+    pub fn realtime_priority(mut self, priority: u8) -> Self {
+        self.priority = Some(i32::from(priority));
+        self
+    }
+
+    /// Only let the thread run on the given CPUs. Only supported on Linux. See
+    /// [`ThreadConfig::validate`] for the CPUs that can be used.
+    pub fn cpu_affinity(mut self, cpus: &[usize]) -> Self {
+        self.cpus = cpus.to_vec();
+        self
+    }
+
+    /// Check that every CPU given to [`ThreadConfig::cpu_affinity`] fits in the CPU set of the OS.
+    /// An invalid affinity is logged and left out when the thread is spawned.
+    pub fn validate(&self) -> io::Result<()> {
+        match self.cpus.iter().find(|&&cpu| cpu >= MAX_CPUS) {
+            Some(cpu) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("CPU {} is out of range, the limit is {}", cpu, MAX_CPUS),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Spawn a thread with this configuration. `default_name` is used if no name was set.
+    pub(crate) fn spawn<F, R>(&self, default_name: &str, f: F) -> JoinHandle<R>
+    where
+        F: 'static + FnOnce() -> R + Send,
+        R: 'static + Send,
+    {
+        let name = self.name.clone().unwrap_or_else(|| default_name.to_owned());
+        let priority = self.priority;
+        let cpus = match self.validate() {
+            Ok(()) => self.cpus.clone(),
+            Err(e) => {
+                log::warn!("Not setting the CPU affinity of {}: {}", name, e);
+                Vec::new()
+            }
+        };
+
+        thread::Builder::new()
+            .name(name.clone())
+            .spawn(move || {
+                if let Some(priority) = priority {
+                    if let Err(e) = set_realtime_priority(priority) {
+                        log::warn!("Unable to set the priority of {}: {}", name, e);
+                    }
+                }
+
+                if !cpus.is_empty() {
+                    if let Err(e) = set_cpu_affinity(&cpus) {
+                        log::warn!("Unable to set the CPU affinity of {}: {}", name, e);
+                    }
+                }
+
+                f()
+            })
+            .expect("Failed to spawn polling thread")
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_realtime_priority(priority: i32) -> io::Result<()> {
+    let param = libc::sched_param {
+        sched_priority: priority,
+    };
+
+    match unsafe { libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) } {
+        0 => Ok(()),
+        err => Err(io::Error::from_raw_os_error(err)),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_cpu_affinity(cpus: &[usize]) -> io::Result<()> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+
+        for &cpu in cpus {
+            libc::CPU_SET(cpu, &mut set);
+        }
+
+        // A pid of 0 applies the mask to the calling thread
+        match libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_realtime_priority(_: i32) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "Real-time priority is only supported on Linux",
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_cpu_affinity(_: &[usize]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "CPU affinity is only supported on Linux",
+    ))
+}
+
+/// Sleep until `deadline` unless `stop` returns true first. The thread is expected to be unparked
+/// when it is asked to stop, so it does not have to sleep out the full duration.
+pub(crate) fn sleep_until<F: Fn() -> bool>(deadline: Instant, stop: F) {
+    loop {
+        if stop() {
+            return;
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+
+        // Parking can wake up early, so keep going until the deadline is reached
+        thread::park_timeout(deadline - now);
+    }
+}
+
+/// Sleep for `duration` unless `stop` returns true first. See [`sleep_until`].
+pub(crate) fn sleep_for<F: Fn() -> bool>(duration: Duration, stop: F) {
+    sleep_until(Instant::now() + duration, stop)
+}
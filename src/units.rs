@@ -0,0 +1,330 @@
+// Copyright 2018 navx-rs Developers.
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Unit-typed views of the values decoded from the navX. Every quantity is wrapped in a newtype so
+//! that mixing units (ex: degrees with radians or G with m/s²) is a compile error. Conversions
+//! between compatible units are done with `From`/`Into`.
+//!
+//! The decoded structs keep their plain fields, so this module only adds accessor methods with the
+//! same names as the fields they wrap. Enable it with the `units` feature.
+
+use crate::register::storage::{Config, LinearAccel, Orientation, RawGyro};
+use crate::serde::{scale_raw, Vector};
+use crate::serial::storage::{
+    DirectionalUpdate, PositionUpdate, RawDataUpdate, StreamConfigurationResponse,
+};
+use std::fmt::{self, Display, Formatter};
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+/// Standard gravity in m/s²
+pub const STANDARD_GRAVITY: f32 = 9.806_65;
+
+/// The magnetometer reports in steps of 0.15 uT
+const MAG_SCALE: f32 = 0.15;
+
+macro_rules! unit {
+    ($(#[$meta:meta])* $name:ident($inner:ty) = $symbol:expr) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, Debug, Default, PartialOrd, PartialEq)]
+        pub struct $name(pub $inner);
+
+        impl $name {
+            pub fn value(self) -> $inner {
+                self.0
+            }
+
+            pub fn abs(self) -> Self {
+                $name(self.0.abs())
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+                write!(f, "{} {}", self.0, $symbol)
+            }
+        }
+
+        impl Add for $name {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self::Output {
+                $name(self.0 + rhs.0)
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, rhs: Self) {
+                self.0 += rhs.0;
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self::Output {
+                $name(self.0 - rhs.0)
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, rhs: Self) {
+                self.0 -= rhs.0;
+            }
+        }
+
+        impl Neg for $name {
+            type Output = Self;
+
+            fn neg(self) -> Self::Output {
+                $name(-self.0)
+            }
+        }
+
+        impl Mul<$inner> for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: $inner) -> Self::Output {
+                $name(self.0 * rhs)
+            }
+        }
+
+        impl Div<$inner> for $name {
+            type Output = Self;
+
+            fn div(self, rhs: $inner) -> Self::Output {
+                $name(self.0 / rhs)
+            }
+        }
+
+        /// The ratio between two values of the same unit is unitless
+        impl Div for $name {
+            type Output = $inner;
+
+            fn div(self, rhs: Self) -> Self::Output {
+                self.0 / rhs.0
+            }
+        }
+    };
+}
+
+/// Implement `From` in both directions for two units measuring the same quantity.
+macro_rules! convert {
+    ($a:ident <-> $b:ident: $to_b:expr, $to_a:expr) => {
+        impl From<$a> for $b {
+            fn from(x: $a) -> Self {
+                $b($to_b(x.0))
+            }
+        }
+
+        impl From<$b> for $a {
+            fn from(x: $b) -> Self {
+                $a($to_a(x.0))
+            }
+        }
+    };
+}
+
+unit!(
+    /// An angle in degrees. This is the unit the navX reports all angles in.
+    Degrees(f32) = "°"
+);
+unit!(
+    /// An angle in radians
+    Radians(f32) = "rad"
+);
+unit!(
+    /// Angular velocity in degrees per second
+    DegreesPerSecond(f32) = "°/s"
+);
+unit!(
+    /// Angular velocity in radians per second
+    RadiansPerSecond(f32) = "rad/s"
+);
+unit!(
+    /// Acceleration in multiples of standard gravity
+    StandardGravity(f32) = "G"
+);
+unit!(
+    /// Acceleration in meters per second squared
+    MetersPerSecondSquared(f32) = "m/s²"
+);
+unit!(
+    /// Velocity in meters per second
+    MetersPerSecond(f64) = "m/s"
+);
+unit!(
+    /// Distance in meters
+    Meters(f64) = "m"
+);
+unit!(
+    /// Temperature in degrees celsius
+    Celsius(f32) = "°C"
+);
+unit!(
+    /// Magnetic field strength in microteslas
+    Microteslas(f32) = "uT"
+);
+unit!(
+    /// Frequency in hertz
+    Hertz(f32) = "Hz"
+);
+
+convert!(Degrees <-> Radians: f32::to_radians, f32::to_degrees);
+convert!(DegreesPerSecond <-> RadiansPerSecond: f32::to_radians, f32::to_degrees);
+convert!(StandardGravity <-> MetersPerSecondSquared:
+    |g| g * STANDARD_GRAVITY,
+    |a| a / STANDARD_GRAVITY
+);
+
+impl Config {
+    pub fn update_rate(&self) -> Hertz {
+        Hertz(f32::from(self.update_rate))
+    }
+
+    pub fn accel_fsr(&self) -> StandardGravity {
+        StandardGravity(f32::from(self.accel_fsr))
+    }
+
+    pub fn gyro_fsr(&self) -> DegreesPerSecond {
+        DegreesPerSecond(f32::from(self.gyro_fsr))
+    }
+}
+
+impl Orientation {
+    pub fn yaw(&self) -> Degrees {
+        Degrees(self.yaw)
+    }
+
+    pub fn pitch(&self) -> Degrees {
+        Degrees(self.pitch)
+    }
+
+    pub fn roll(&self) -> Degrees {
+        Degrees(self.roll)
+    }
+
+    pub fn compass_heading(&self) -> Degrees {
+        Degrees(self.compass_heading)
+    }
+
+    pub fn fused_heading(&self) -> Degrees {
+        Degrees(self.fused_heading)
+    }
+}
+
+impl LinearAccel {
+    pub fn accel(&self) -> Vector<StandardGravity> {
+        self.accel.map(StandardGravity)
+    }
+}
+
+impl RawGyro {
+    /// The raw gyro reading scaled by the gyro full scale range.
+    pub fn gyro(&self, fsr: DegreesPerSecond) -> Vector<DegreesPerSecond> {
+        self.gyro.map(|x| DegreesPerSecond(scale_raw(x, fsr.0)))
+    }
+}
+
+impl DirectionalUpdate {
+    pub fn yaw(&self) -> Degrees {
+        Degrees(self.yaw)
+    }
+
+    pub fn pitch(&self) -> Degrees {
+        Degrees(self.pitch)
+    }
+
+    pub fn roll(&self) -> Degrees {
+        Degrees(self.roll)
+    }
+
+    pub fn compass_heading(&self) -> Degrees {
+        Degrees(self.compass_heading)
+    }
+}
+
+impl RawDataUpdate {
+    /// The raw gyro reading scaled by the gyro full scale range.
+    pub fn gyro(&self, fsr: DegreesPerSecond) -> Vector<DegreesPerSecond> {
+        self.gyro.map(|x| DegreesPerSecond(scale_raw(x, fsr.0)))
+    }
+
+    /// The raw accelerometer reading scaled by the accelerometer full scale range.
+    pub fn acceleration(&self, fsr: StandardGravity) -> Vector<StandardGravity> {
+        self.acceleration
+            .map(|x| StandardGravity(scale_raw(x, fsr.0)))
+    }
+
+    pub fn magnetometer(&self) -> Vector<Microteslas> {
+        self.magnetometer
+            .map(|x| Microteslas(f32::from(x) * MAG_SCALE))
+    }
+
+    pub fn temperature(&self) -> Celsius {
+        Celsius(self.temperature)
+    }
+}
+
+impl PositionUpdate {
+    pub fn yaw(&self) -> Degrees {
+        Degrees(self.yaw)
+    }
+
+    pub fn pitch(&self) -> Degrees {
+        Degrees(self.pitch)
+    }
+
+    pub fn roll(&self) -> Degrees {
+        Degrees(self.roll)
+    }
+
+    pub fn compass_heading(&self) -> Degrees {
+        Degrees(self.compass_heading)
+    }
+
+    pub fn altitude(&self) -> Meters {
+        Meters(self.altitude)
+    }
+
+    pub fn fused_heading(&self) -> Degrees {
+        Degrees(self.fused_heading)
+    }
+
+    pub fn linear_accel(&self) -> Vector<StandardGravity> {
+        self.linear_accel.map(StandardGravity)
+    }
+
+    pub fn linear_velocity(&self) -> Vector<MetersPerSecond> {
+        self.linear_velocity.map(MetersPerSecond)
+    }
+
+    pub fn displacement(&self) -> Vector<Meters> {
+        self.displacement.map(Meters)
+    }
+
+    pub fn mpu_temp(&self) -> Celsius {
+        Celsius(self.mpu_temp)
+    }
+}
+
+impl StreamConfigurationResponse {
+    pub fn gyro_fsr(&self) -> DegreesPerSecond {
+        DegreesPerSecond(f32::from(self.gyro_fsr))
+    }
+
+    pub fn accel_fsr(&self) -> StandardGravity {
+        StandardGravity(f32::from(self.accel_fsr))
+    }
+
+    pub fn update_rate(&self) -> Hertz {
+        Hertz(f32::from(self.update_rate))
+    }
+
+    pub fn calibrated_yaw_offset(&self) -> Degrees {
+        Degrees(self.calibrated_yaw_offset)
+    }
+}
@@ -1,13 +1,204 @@
-use parking_lot::{Mutex, RwLock};
-use std::error::Error;
+use parking_lot::{Mutex, MutexGuard};
 use std::io::{self, ErrorKind};
 use std::ops::{Deref, DerefMut};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::thread::{spawn, JoinHandle};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
+use crate::cell::LatestCell;
+use crate::history::History;
+use crate::stats::LinkStats;
+use crate::subscribe::{Capacity, Publish, Subscribers, Subscription};
+use crate::thread::{sleep_for, sleep_until, ThreadConfig};
 use crate::Request;
 
+/// The update rate the navX uses unless it is configured otherwise (Hz).
+pub const DEFAULT_UPDATE_RATE: u8 = 50;
+
+/// When syncing to the board timestamp, a stale sample is retried after this fraction of the
+/// period instead of waiting for the next full period.
+const STALE_RETRY_DIVISOR: u32 = 8;
+
+/// How a watcher responds to read errors. Interrupted errors are never counted since they are often
+/// caused by a short read and do not indicate a problem with the connection.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// The number of consecutive failed reads allowed before the watcher stops for good
+    pub max_failures: usize,
+    /// How long to wait before retrying after the first failure
+    pub initial_backoff: Duration,
+    /// The upper limit on the time between retries
+    pub max_backoff: Duration,
+    /// The factor the backoff grows by with every consecutive failure
+    pub multiplier: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_failures: 10,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+            multiplier: 2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Stop watching on the first error.
+    pub fn never() -> Self {
+        Self {
+            max_failures: 0,
+            ..Self::default()
+        }
+    }
+
+    /// The time to wait after the given number of consecutive failures
+    pub fn backoff(&self, failures: usize) -> Duration {
+        let mut backoff = self.initial_backoff;
+
+        for _ in 1..failures {
+            if backoff >= self.max_backoff {
+                break;
+            }
+            backoff *= self.multiplier;
+        }
+
+        backoff.min(self.max_backoff)
+    }
+}
+
+/// A summary of the errors a watcher has run into.
+#[derive(Debug)]
+pub struct ErrorStats {
+    /// The number of failed reads since the last successful one
+    pub consecutive: usize,
+    /// The total number of failed reads
+    pub total: usize,
+    /// The most recent error
+    pub last: Option<io::Error>,
+}
+
+impl Clone for ErrorStats {
+    fn clone(&self) -> Self {
+        Self {
+            consecutive: self.consecutive,
+            total: self.total,
+            last: self.last.as_ref().map(clone_err),
+        }
+    }
+}
+
+/// Configuration for a watcher. By default values are polled at the update rate reported by the
+/// provider, or at the navX's default update rate if it does not know one.
+pub struct Builder<T> {
+    period: Option<Duration>,
+    retry: RetryPolicy,
+    timestamp: Option<fn(&T) -> u32>,
+    history: Option<Arc<Mutex<History<T>>>>,
+    listeners: Vec<Publish<T>>,
+    thread: ThreadConfig,
+    stats: LinkStats,
+}
+
+impl<T> Default for Builder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Builder<T> {
+    pub fn new() -> Self {
+        Self {
+            period: None,
+            retry: RetryPolicy::default(),
+            timestamp: None,
+            history: None,
+            listeners: Vec::new(),
+            thread: ThreadConfig::new(),
+            stats: LinkStats::new(),
+        }
+    }
+
+    /// Set the time between the start of two consecutive reads. Reads are scheduled at a fixed
+    /// rate, so the time spent reading does not cause the schedule to drift.
+    pub fn period(mut self, period: Duration) -> Self {
+        self.period = Some(period);
+        self
+    }
+
+    /// Set the polling period from a rate in Hz, in place of the rate reported by the provider.
+    pub fn rate(self, hz: u32) -> Self {
+        self.period(Duration::from_secs(1) / hz.max(1))
+    }
+
+    /// Set how read errors are handled. By default a watcher retries with an exponential backoff
+    /// and only stops after a number of consecutive failures.
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Use the board timestamp of each sample to stay in sync with the board. If a read returns a
+    /// sample with the same timestamp as the last one, the board has not updated yet, so the
+    /// sample is dropped and the read is retried shortly after instead of waiting a full period.
+    /// The schedule is then re-aligned to the first read that sees a new timestamp.
+    pub fn sync_to_board(mut self, timestamp: fn(&T) -> u32) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Record retries and sample timing into the given statistics. Pass the handle from the
+    /// transport being watched to keep every statistic of the link in one place.
+    pub fn stats(mut self, stats: LinkStats) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Name the polling thread. Defaults to `navx-watcher`.
+    pub fn name<N: Into<String>>(mut self, name: N) -> Self {
+        self.thread = self.thread.name(name);
+        self
+    }
+
+    /// Run the polling thread with a `SCHED_FIFO` priority. See
+    /// [`ThreadConfig::realtime_priority`].
+    pub fn realtime_priority(mut self, priority: u8) -> Self {
+        self.thread = self.thread.realtime_priority(priority);
+        self
+    }
+
+    /// Pin the polling thread to the given CPUs. See [`ThreadConfig::cpu_affinity`].
+    pub fn cpu_affinity(mut self, cpus: &[usize]) -> Self {
+        self.thread = self.thread.cpu_affinity(cpus);
+        self
+    }
+}
+
+impl<T: 'static + Clone + Send> Builder<T> {
+    /// Keep a history of the last `capacity` samples which can be queried by time. See
+    /// [`Watcher::history`].
+    pub fn history(mut self, capacity: usize) -> Self {
+        let history = Arc::new(Mutex::new(History::new(capacity)));
+        let writer = history.clone();
+
+        self.listeners.push(Box::new(move |sample| {
+            writer.lock().push(sample.clone());
+            true
+        }));
+        self.history = Some(history);
+        self
+    }
+}
+
+impl<T: 'static + Send> Builder<T> {
+    /// Start watching values from the given provider.
+    pub fn watch<S: 'static + Request<T> + Send>(self, provider: S) -> Watched<T, S> {
+        Watched::from_watcher(Watcher::with_builder(provider, self))
+    }
+}
+
 /// Trait to request that a value be watched.
 pub trait Watch<T> {
     /// The type that provides the values to cache
@@ -18,84 +209,414 @@ pub trait Watch<T> {
     fn watch(self) -> Watched<T, Self::Provider>;
 }
 
+/// A value along with when it was read.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sample<T> {
+    /// The time the read of this sample started
+    pub host_time: Instant,
+    /// The board timestamp (ms) of the sample if it has one
+    pub board_timestamp: Option<u32>,
+    pub value: T,
+}
+
+impl<T> Sample<T> {
+    /// A sample of another value taken at the same time as this one.
+    pub fn with_value<U>(&self, value: U) -> Sample<U> {
+        Sample {
+            host_time: self.host_time,
+            board_timestamp: self.board_timestamp,
+            value,
+        }
+    }
+
+    /// The seconds between an earlier sample and this one. Board timestamps are used when both
+    /// samples have one, since they are not affected by delays on the host. The difference handles
+    /// the board timestamp wrapping around, and is negative if `earlier` is actually newer.
+    pub fn seconds_since<U>(&self, earlier: &Sample<U>) -> f64 {
+        match (earlier.board_timestamp, self.board_timestamp) {
+            (Some(earlier), Some(now)) => f64::from(now.wrapping_sub(earlier) as i32) / 1000.0,
+            _ => self
+                .host_time
+                .saturating_duration_since(earlier.host_time)
+                .as_secs_f64(),
+        }
+    }
+}
+
+/// The most recent result of reading a value. This is shared between the thread doing the reading
+/// and the handles used to access the value. Values are kept in a [`LatestCell`] so reading them
+/// never takes a lock held by the reading thread. Errors are kept separately and only checked
+/// through a flag.
+pub struct Slot<T> {
+    latest: LatestCell<T>,
+    failed: AtomicBool,
+    error: Mutex<Option<io::Error>>,
+    subscribers: Subscribers<T>,
+}
+
+impl<T> Default for Slot<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Slot<T> {
+    pub fn new() -> Self {
+        Self {
+            latest: LatestCell::new(),
+            failed: AtomicBool::new(false),
+            error: Mutex::new(None),
+            subscribers: Subscribers::new(),
+        }
+    }
+
+    /// Store a new value, or an error in place of the value.
+    pub fn store(&self, value: io::Result<T>) {
+        match value {
+            Ok(value) => {
+                self.latest.store(value);
+                self.failed.store(false, Ordering::Release);
+            }
+            Err(e) => {
+                *self.error.lock() = Some(e);
+                self.failed.store(true, Ordering::Release);
+            }
+        }
+    }
+
+    /// Publish a newly read sample to all subscribers and store it as the latest value.
+    pub fn update(&self, sample: Sample<T>) {
+        self.subscribers.publish(&sample);
+        self.store(Ok(sample.value));
+    }
+
+    /// The number of values stored so far
+    pub fn version(&self) -> usize {
+        self.latest.version()
+    }
+
+    /// The error stored in place of the value, if there is one
+    fn error(&self) -> Option<io::Error> {
+        if !self.failed.load(Ordering::Acquire) {
+            return None;
+        }
+
+        self.error.lock().as_ref().map(clone_err)
+    }
+
+    /// The error that stopped updates to this slot, if there was one. Interrupted errors are not
+    /// considered fatal so they are ignored.
+    pub fn failure(&self) -> Option<io::Error> {
+        self.error().filter(|e| e.kind() != ErrorKind::Interrupted)
+    }
+}
+
+impl<T: 'static + Clone + Send> Slot<T> {
+    /// Receive every sample read from now on.
+    pub fn subscribe(&self, capacity: Capacity) -> Subscription<T> {
+        self.subscribers.subscribe(capacity)
+    }
+}
+
+impl<T> Slot<T> {
+    /// Call a function with every sample read from now on. The function is run on the polling
+    /// thread, so it should return quickly.
+    pub fn on_sample<F: 'static + Fn(&Sample<T>) + Send>(&self, f: F) {
+        self.subscribers.listen(Box::new(move |sample| {
+            f(sample);
+            true
+        }));
+    }
+}
+
+impl<T: Copy> Slot<T> {
+    /// Get the latest recorded value. Before the first value is read, an interrupted error is
+    /// returned instead.
+    pub fn get(&self) -> io::Result<T> {
+        if let Some(e) = self.error() {
+            return Err(e);
+        }
+
+        match self.latest.load() {
+            Some(value) => Ok(value),
+            None => Err(io::Error::new(
+                ErrorKind::Interrupted,
+                "Value has not been read yet",
+            )),
+        }
+    }
+
+    /// Checks if a valid value has been collected yet.
+    pub fn is_ready(&self) -> bool {
+        match self.get() {
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => false,
+            // Return true even if an error is found to prevent programs from hanging
+            _ => true,
+        }
+    }
+}
+
+type ErrorListener = Box<dyn Fn(&io::Error) + Send>;
+
 pub struct Watcher<T, S> {
     inner: Mutex<Option<S>>,
-    cache: RwLock<io::Result<T>>,
+    cache: Slot<T>,
     stop_indicator: AtomicBool,
+    period: Duration,
+    retry: RetryPolicy,
+    errors: Mutex<ErrorStats>,
+    error_listeners: Mutex<Vec<ErrorListener>>,
+    timestamp: Option<fn(&T) -> u32>,
+    overruns: AtomicUsize,
+    last_overrun: Mutex<Option<Duration>>,
+    history: Option<Arc<Mutex<History<T>>>>,
+    thread: ThreadConfig,
+    stats: LinkStats,
 }
 
 /// Watcher is guaranteed to be thread safe because all of its contents are thread safe. However, it
 /// will require an Arc to send to another thread.
 unsafe impl<T, S> Sync for Watcher<T, S> {}
 
-impl<T, S> Watcher<T, S> {
+impl<T, S: Request<T>> Watcher<T, S> {
     pub fn new(inner: S) -> Self {
+        Self::with_builder(inner, Builder::new())
+    }
+
+    pub fn with_builder(mut inner: S, builder: Builder<T>) -> Self {
+        let cache = Slot::new();
+        let period = builder.period.unwrap_or_else(|| {
+            let rate = inner
+                .update_rate()
+                .unwrap_or_else(|| u32::from(DEFAULT_UPDATE_RATE));
+            Duration::from_secs(1) / rate.max(1)
+        });
+
+        for listener in builder.listeners {
+            cache.subscribers.listen(listener);
+        }
+
         Self {
             inner: Mutex::new(Some(inner)),
-            cache: RwLock::new(Err(io::Error::new(
-                ErrorKind::Interrupted,
-                "Value has not been read yet",
-            ))),
+            cache,
             stop_indicator: AtomicBool::new(false),
+            period,
+            retry: builder.retry,
+            errors: Mutex::new(ErrorStats {
+                consecutive: 0,
+                total: 0,
+                last: None,
+            }),
+            error_listeners: Mutex::new(Vec::new()),
+            timestamp: builder.timestamp,
+            overruns: AtomicUsize::new(0),
+            last_overrun: Mutex::new(None),
+            history: builder.history,
+            thread: builder.thread,
+            stats: builder.stats,
         }
     }
 }
 
+impl<T, S> Watcher<T, S> {
+    /// The recorded history of samples if this watcher was built with one. The watcher thread can
+    /// not record new samples while the returned guard is held, so it should be dropped quickly.
+    pub fn history(&self) -> Option<MutexGuard<'_, History<T>>> {
+        self.history.as_ref().map(|history| history.lock())
+    }
+
+    /// A handle to the statistics recorded by this watcher. See [`Builder::stats`].
+    pub fn stats(&self) -> LinkStats {
+        self.stats.clone()
+    }
+
+    /// The time between the start of two consecutive reads
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// The errors encountered so far. These are tracked while the watcher keeps retrying, so they
+    /// can be checked before the watcher gives up.
+    pub fn errors(&self) -> ErrorStats {
+        self.errors.lock().clone()
+    }
+
+    /// Record a failed read and get the number of consecutive failures.
+    fn record_error(&self, err: &io::Error) -> usize {
+        for listener in self.error_listeners.lock().iter() {
+            listener(err);
+        }
+
+        let mut errors = self.errors.lock();
+        errors.consecutive += 1;
+        errors.total += 1;
+        errors.last = Some(clone_err(err));
+        errors.consecutive
+    }
+
+    /// Call a function with every sample read from now on. See [`Slot::on_sample`].
+    pub fn on_sample<F: 'static + Fn(&Sample<T>) + Send>(&self, f: F) {
+        self.cache.on_sample(f)
+    }
+
+    /// Call a function with every failed read from now on. Interrupted errors are not reported.
+    /// The function is run on the polling thread, so it should return quickly.
+    pub fn on_error<F: 'static + Fn(&io::Error) + Send>(&self, f: F) {
+        self.error_listeners.lock().push(Box::new(f));
+    }
+
+    /// The number of reads that took longer than the polling period
+    pub fn overruns(&self) -> usize {
+        self.overruns.load(Ordering::SeqCst)
+    }
+
+    /// How long the most recent overrunning read took
+    pub fn last_overrun(&self) -> Option<Duration> {
+        *self.last_overrun.lock()
+    }
+
+    /// Receive every new sample read by this watcher. Stale samples skipped while syncing to the
+    /// board are not published.
+    pub fn subscribe(&self, capacity: Capacity) -> Subscription<T>
+    where
+        T: 'static + Clone + Send,
+    {
+        self.cache.subscribe(capacity)
+    }
+
+    /// Check if a sample is a repeat of the last one according to the board timestamp.
+    fn is_stale(&self, value: &T, last_timestamp: &mut Option<u32>) -> bool {
+        let timestamp = match self.timestamp {
+            Some(f) => f(value),
+            None => return false,
+        };
+
+        let stale = *last_timestamp == Some(timestamp);
+        *last_timestamp = Some(timestamp);
+        stale
+    }
+}
+
 impl<T: 'static + Send, S: 'static + Request<T> + Send> Watcher<T, S> {
     pub fn start(self: Arc<Self>) -> JoinHandle<()> {
-        spawn(move || loop {
-            if self.stop_indicator.load(Ordering::SeqCst) {
-                return;
-            }
-
-            let value_read = self.inner.lock().as_mut().unwrap().read();
+        self.thread.clone().spawn("navx-watcher", move || {
+            let stopped = || self.stop_indicator.load(Ordering::SeqCst);
+            let mut next = Instant::now();
+            let mut last_timestamp = None;
+            let mut recovering = false;
 
-            match value_read {
-                Ok(v) => *self.cache.write() = Ok(v),
-                Err(ref e) if e.kind() == ErrorKind::Interrupted => (),
-                x => {
-                    *self.cache.write() = x;
-                    self.stop_indicator.store(true, Ordering::SeqCst);
+            loop {
+                if self.stop_indicator.load(Ordering::SeqCst) {
                     return;
                 }
+
+                let read_start = Instant::now();
+                let value_read = {
+                    let mut guard = self.inner.lock();
+                    let inner = guard.as_mut().unwrap();
+
+                    // Make sure the connection is still good before trusting the data after errors
+                    if recovering {
+                        self.stats.record_retry();
+                        Request::<T>::recover(inner).and_then(|_| inner.read())
+                    } else {
+                        inner.read()
+                    }
+                };
+                let read_time = read_start.elapsed();
+
+                match value_read {
+                    Ok(ref v) if self.is_stale(v, &mut last_timestamp) => {
+                        // The board has not updated yet so check again shortly
+                        sleep_for(self.period / STALE_RETRY_DIVISOR, stopped);
+                        continue;
+                    }
+                    Ok(value) => {
+                        recovering = false;
+                        self.errors.lock().consecutive = 0;
+                        self.stats.record_sample(read_start);
+
+                        self.cache.update(Sample {
+                            host_time: read_start,
+                            board_timestamp: self.timestamp.map(|f| f(&value)),
+                            value,
+                        });
+
+                        // Align the schedule with the moment new data showed up
+                        if self.timestamp.is_some() {
+                            next = read_start;
+                        }
+                    }
+                    Err(ref e) if e.kind() == ErrorKind::Interrupted => (),
+                    Err(e) => {
+                        if self.record_error(&e) > self.retry.max_failures {
+                            self.cache.store(Err(e));
+                            self.stop_indicator.store(true, Ordering::SeqCst);
+                            return;
+                        }
+
+                        recovering = true;
+                        sleep_for(self.retry.backoff(self.errors.lock().consecutive), stopped);
+                        next = Instant::now();
+                        continue;
+                    }
+                }
+
+                if read_time > self.period {
+                    self.overruns.fetch_add(1, Ordering::SeqCst);
+                    *self.last_overrun.lock() = Some(read_time);
+                }
+
+                // Advance by a fixed step so time spent reading does not accumulate as drift. If
+                // the deadline was already missed, skip ahead instead of trying to catch up.
+                next += self.period;
+                let now = Instant::now();
+
+                if next > now {
+                    sleep_until(next, stopped);
+                } else {
+                    next = now;
+                }
             }
         })
     }
 }
 
 /// A utility struct to watch a value collected by the NavX.
+/// Dropping it stops the watcher thread and waits for it to finish.
 pub struct Watched<T, S> {
     inner: Arc<Watcher<T, S>>,
-    join_handle: JoinHandle<()>,
+    join_handle: Option<JoinHandle<()>>,
 }
 
 impl<T: 'static + Send, S: 'static + Request<T> + Send> Watched<T, S> {
+    /// Start watching a provider at the navX's default update rate. Use a [`Builder`] to configure
+    /// the polling rate.
     pub fn new(inner: S) -> Self {
-        let watcher = Arc::new(Watcher::new(inner));
+        Self::from_watcher(Watcher::new(inner))
+    }
+
+    fn from_watcher(watcher: Watcher<T, S>) -> Self {
+        let watcher = Arc::new(watcher);
 
         Self {
             inner: watcher.clone(),
-            join_handle: watcher.start(),
+            join_handle: Some(watcher.start()),
         }
     }
 }
 
 impl<T, S> Watched<T, S> {
-    /// Stops watching the value and makes the watcher thread join this thread. If an IO error
-    /// occurred while watching, that error will be returned instead.
-    pub fn stop(self) -> io::Result<S> {
-        self.stop_indicator.store(true, Ordering::SeqCst);
-
-        if self.join_handle.join().is_err() {
+    /// Stops watching the value and makes the watcher thread join this thread. If the watcher gave
+    /// up after too many IO errors, the last error will be returned instead.
+    pub fn stop(mut self) -> io::Result<S> {
+        if !self.join() {
             return Err(io::Error::new(ErrorKind::BrokenPipe, ""));
         }
 
-        match &*self.inner.cache.read() {
-            Ok(_) => Ok(self.inner.inner.lock().take().unwrap()),
-            Err(ref e) if e.kind() == ErrorKind::Interrupted => {
-                Ok(self.inner.inner.lock().take().unwrap())
-            }
-            Err(ref e) => Err(clone_err(e)),
+        match self.inner.cache.failure() {
+            Some(e) => Err(e),
+            None => Ok(self.inner.inner.lock().take().unwrap()),
         }
     }
 
@@ -103,6 +624,33 @@ impl<T, S> Watched<T, S> {
     pub fn is_stopped(&self) -> bool {
         self.inner.stop_indicator.load(Ordering::SeqCst)
     }
+
+    /// Signal the watcher thread to stop and wait for it. Returns false if the thread panicked.
+    fn join(&mut self) -> bool {
+        self.inner.stop_indicator.store(true, Ordering::SeqCst);
+        // A subscriber that stopped receiving would otherwise keep the thread from ever finishing
+        self.inner.cache.subscribers.close();
+
+        let handle = match self.join_handle.take() {
+            Some(x) => x,
+            None => return true,
+        };
+
+        // A watcher dropped from one of its own listeners can't wait for itself
+        if handle.thread().id() == thread::current().id() {
+            return true;
+        }
+
+        // Wake the thread up if it is sleeping between reads
+        handle.thread().unpark();
+        handle.join().is_ok()
+    }
+}
+
+impl<T, S> Drop for Watched<T, S> {
+    fn drop(&mut self) {
+        self.join();
+    }
 }
 
 impl<T, S> Deref for Watched<T, S> {
@@ -127,15 +675,8 @@ fn clone_err(err: &io::Error) -> io::Error {
         return io::Error::from_raw_os_error(os_err);
     }
 
-    // Custom errors can't be retrieved, so make sure to preserve the debug print
-    io::Error::new(err.kind(), err.description().to_owned())
-}
-
-fn clone_io_result<T: Copy>(res: &io::Result<T>) -> io::Result<T> {
-    match res {
-        Ok(x) => Ok(*x),
-        Err(e) => Err(clone_err(e)),
-    }
+    // Custom errors can't be retrieved, so make sure to preserve the message
+    io::Error::new(err.kind(), err.to_string())
 }
 
 impl<T: Copy, S> Watched<T, S> {
@@ -145,15 +686,11 @@ impl<T: Copy, S> Watched<T, S> {
     /// interrupted error will be returned. This error is not fatal and will be replaced with a
     /// valid answer once one is found.
     pub fn get(&self) -> io::Result<T> {
-        clone_io_result(&*self.cache.read())
+        self.cache.get()
     }
 
     /// Checks if this watcher has collected a valid value yet.
     pub fn is_ready(&self) -> bool {
-        match self.get() {
-            Err(ref e) if e.kind() == ErrorKind::Interrupted => false,
-            // Return true even if an error is found to prevent programs from hanging
-            _ => true,
-        }
+        self.cache.is_ready()
     }
 }
@@ -0,0 +1,83 @@
+//! Runs both host side filters on synthetic sensor readings and checks that they settle where the
+//! board physically is. Yaw is read back clockwise like the board reports it.
+
+use navx::ahrs::{Ahrs, DEFAULT_MADGWICK_BETA, DEFAULT_MAHONY_KI, DEFAULT_MAHONY_KP};
+use navx::heading::YawSource;
+use navx::math::Quaternion;
+use navx::serde::Vector;
+
+const DT: f32 = 0.01;
+const UP: Vector<f32> = Vector {
+    x: 0.0,
+    y: 0.0,
+    z: 1.0,
+};
+
+fn filters() -> Vec<Ahrs> {
+    vec![
+        Ahrs::madgwick(2000, DEFAULT_MADGWICK_BETA),
+        Ahrs::mahony(2000, DEFAULT_MAHONY_KP, DEFAULT_MAHONY_KI),
+    ]
+}
+
+/// Level the filter on a flat board, then feed the same readings for `seconds`.
+fn run(ahrs: &mut Ahrs, gyro: Vector<f32>, accel: Vector<f32>, seconds: f32) -> Quaternion {
+    ahrs.update_rates(Vector::zero(), UP, None, 0.0);
+
+    for _ in 0..(seconds / DT).round() as usize {
+        ahrs.update_rates(gyro, accel, None, DT);
+    }
+
+    ahrs.orientation()
+}
+
+/// A board resting at a tilt is levelled by the accelerometer alone.
+#[test]
+fn tilted_board_levels() {
+    let tilted = Quaternion::from_yaw_pitch_roll(0.0, 15.0, -20.0);
+    let accel = tilted.gravity();
+
+    for mut ahrs in filters() {
+        let gravity = run(&mut ahrs, Vector::zero(), accel, 30.0).gravity();
+
+        assert!(
+            (gravity - accel).norm() < 1e-2,
+            "{:?}: expected gravity {:?}, got {:?}",
+            ahrs.algorithm(),
+            accel,
+            gravity
+        );
+    }
+}
+
+/// Turning clockwise at 90 degrees/sec for a second is a negative rate about the up axis and ends
+/// at a yaw of 90.
+#[test]
+fn constant_rate_integrates_to_yaw() {
+    for mut ahrs in filters() {
+        let yaw = run(&mut ahrs, Vector::new(0.0, 0.0, -90.0), UP, 1.0).yaw_degrees();
+
+        assert!(
+            (yaw - 90.0).abs() < 0.1,
+            "{:?}: expected 90, got {}",
+            ahrs.algorithm(),
+            yaw
+        );
+    }
+}
+
+/// A gyro bias drifts the yaw of a still board unless it is subtracted.
+#[test]
+fn gyro_bias_is_removed() {
+    let bias = Vector::new(0.0, 0.0, 2.0);
+
+    for mut ahrs in filters() {
+        let yaw = run(&mut ahrs, bias, UP, 10.0).yaw_degrees();
+        assert!((yaw + 20.0).abs() < 0.1, "expected -20, got {}", yaw);
+
+        ahrs.reset();
+        ahrs.set_gyro_bias(bias);
+        let yaw = run(&mut ahrs, bias, UP, 10.0).yaw_degrees();
+        assert!(yaw.abs() < 1e-3, "expected 0, got {}", yaw);
+    }
+}
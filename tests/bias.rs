@@ -0,0 +1,91 @@
+//! Feeds a stationary robot whose gyro reads a constant bias and checks that the drift it causes in
+//! the yaw is cancelled. The gyro is right handed while the board yaw increases clockwise, so a
+//! positive Z bias makes the yaw drift down.
+
+use navx::bias::{BiasConfig, GyroBiasEstimator};
+use navx::serde::{SensorStatus, Vector};
+use navx::watch::Sample;
+use std::time::Instant;
+
+/// Degrees per second read by the gyro about the up axis while still
+const BIAS: f32 = 0.5;
+/// Milliseconds between samples
+const PERIOD: u32 = 10;
+
+fn sample<T>(value: T, timestamp: u32) -> Sample<T> {
+    Sample {
+        host_time: Instant::now(),
+        board_timestamp: Some(timestamp),
+        value,
+    }
+}
+
+/// Observe a stationary robot until the estimate is ready, returning the next timestamp.
+fn settle(estimator: &GyroBiasEstimator) -> u32 {
+    let config = estimator.config();
+    let mut timestamp = 0;
+
+    // Gyro samples only count once the acceleration window is full
+    for _ in 0..config.accel_window + config.min_samples {
+        estimator.observe_status(&sample(SensorStatus::empty(), timestamp));
+        estimator.observe_accel(&sample(Vector::new(0.0, 0.0, 0.0), timestamp));
+        estimator.observe_gyro(&sample(Vector::new(0.0, 0.0, BIAS), timestamp));
+        timestamp += PERIOD;
+    }
+
+    timestamp
+}
+
+#[test]
+fn estimates_bias() {
+    let estimator = GyroBiasEstimator::new(BiasConfig::default());
+    settle(&estimator);
+
+    let bias = estimator.bias().unwrap();
+    assert!((bias.z - BIAS).abs() < 1e-4);
+    assert!(estimator.is_stationary());
+}
+
+/// A positive bias makes the yaw drift down by 30 degrees per minute.
+#[test]
+fn drift_rate_has_yaw_sign() {
+    let estimator = GyroBiasEstimator::new(BiasConfig::default());
+    settle(&estimator);
+
+    assert!((estimator.drift_rate().unwrap() + BIAS * 60.0).abs() < 1e-2);
+}
+
+/// The board yaw drifts at -BIAS, and the corrected yaw stays where it started.
+#[test]
+fn corrected_yaw_stays_flat() {
+    let estimator = GyroBiasEstimator::new(BiasConfig::default());
+    let start = settle(&estimator);
+
+    let initial = 10.0;
+    for step in 0..=6000 {
+        let elapsed = step * PERIOD;
+        let yaw = initial - BIAS * elapsed as f32 / 1000.0;
+        let yaw = (yaw + 180.0).rem_euclid(360.0) - 180.0;
+        let corrected = estimator.correct_yaw(&sample(yaw, start + elapsed));
+
+        assert!(
+            (corrected - initial).abs() < 1e-2,
+            "yaw drifted to {} after {}ms",
+            corrected,
+            elapsed
+        );
+    }
+
+    assert!((estimator.correction() - 30.0).abs() < 1e-3);
+}
+
+/// Without correction the raw yaw is passed through.
+#[test]
+fn correction_can_be_disabled() {
+    let estimator = GyroBiasEstimator::new(BiasConfig::default());
+    let start = settle(&estimator);
+    estimator.set_apply_correction(false);
+
+    estimator.correct_yaw(&sample(10.0, start));
+    assert_eq!(estimator.correct_yaw(&sample(9.5, start + 1000)), 9.5);
+}
@@ -0,0 +1,139 @@
+//! Feeds the collision detector synthetic acceleration samples with board timestamps and checks
+//! which changes in acceleration are reported as impacts.
+
+use navx::collision::{CollisionDetector, CollisionEvent};
+use navx::serde::Vector;
+use navx::subscribe::Capacity;
+use navx::watch::Sample;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// An acceleration in G measured `ms` milliseconds into the test
+fn sample(x: f32, y: f32, ms: u32) -> Sample<Vector<f32>> {
+    Sample {
+        host_time: Instant::now(),
+        board_timestamp: Some(ms),
+        value: Vector::new(x, y, 0.0),
+    }
+}
+
+/// A detector with a threshold of 10 G/s along X and 20 G/s along Y
+fn detector() -> CollisionDetector {
+    CollisionDetector::new(10.0, 20.0)
+}
+
+/// The first sample only sets the baseline, and small changes are not impacts.
+#[test]
+fn below_threshold() {
+    let detector = detector();
+
+    assert_eq!(detector.observe(&sample(5.0, 5.0, 0)), None);
+    assert_eq!(detector.observe(&sample(5.09, 5.19, 10)), None);
+    assert!(!detector.is_collision_detected());
+}
+
+/// Each axis is compared with its own threshold.
+#[test]
+fn per_axis_thresholds() {
+    let detector = detector();
+    detector.observe(&sample(0.0, 0.0, 0));
+
+    // 15 G/s is over the X threshold but under the Y one
+    assert!(detector.observe(&sample(0.0, 0.15, 10)).is_none());
+
+    let event = detector.observe(&sample(0.15, 0.15, 20)).unwrap();
+    assert!((event.value.jerk_x - 15.0).abs() < 1e-3);
+    assert!(event.value.jerk_y.abs() < 1e-3);
+    assert_eq!(event.board_timestamp, Some(20));
+
+    detector.set_thresholds(20.0, 10.0);
+    assert_eq!(detector.thresholds(), (20.0, 10.0));
+    detector.reset();
+    detector.observe(&sample(0.0, 0.0, 100));
+    assert!(detector.observe(&sample(0.0, 0.15, 110)).is_some());
+}
+
+/// Hitting a wall while driving forward pushes the robot back along -Y.
+#[test]
+fn head_on_direction() {
+    let detector = detector();
+    detector.observe(&sample(0.0, 0.5, 0));
+
+    let event = detector.observe(&sample(0.0, -1.5, 20)).unwrap().value;
+    assert!((event.jerk_y + 100.0).abs() < 1e-3);
+    assert!((event.magnitude - 100.0).abs() < 1e-3);
+    assert!((event.direction + 90.0).abs() < 1e-3);
+}
+
+/// Jerk within the holdoff of a collision is part of the same impact.
+#[test]
+fn holdoff() {
+    let detector = detector().with_holdoff(Duration::from_millis(50));
+    detector.observe(&sample(0.0, 0.0, 0));
+
+    assert!(detector.observe(&sample(1.0, 0.0, 10)).is_some());
+    assert!(detector.observe(&sample(0.0, 0.0, 20)).is_none());
+    assert!(detector.observe(&sample(1.0, 0.0, 59)).is_none());
+    assert!(detector.observe(&sample(0.0, 0.0, 60)).is_some());
+}
+
+/// A sample with the same timestamp as the previous one is ignored.
+#[test]
+fn repeated_timestamp() {
+    let detector = detector();
+    detector.observe(&sample(0.0, 0.0, 10));
+
+    assert!(detector.observe(&sample(1.0, 0.0, 10)).is_none());
+    assert!(detector.observe(&sample(0.0, 0.0, 20)).is_none());
+}
+
+/// A sample older than the previous one is ignored instead of being read as a huge gap.
+#[test]
+fn earlier_timestamp() {
+    let detector = detector();
+    detector.observe(&sample(0.0, 0.0, 10));
+
+    assert!(detector.observe(&sample(1.0, 0.0, 5)).is_none());
+    assert!(detector.observe(&sample(0.0, 0.0, 20)).is_none());
+    assert!(detector.observe(&sample(1.0, 0.0, 30)).is_some());
+}
+
+/// The flag holds the latest collision until it is taken.
+#[test]
+fn take_collision() {
+    let detector = detector().with_holdoff(Duration::from_millis(0));
+    assert_eq!(detector.take_collision(), None);
+
+    detector.observe(&sample(0.0, 0.0, 0));
+    detector.observe(&sample(1.0, 0.0, 10));
+    detector.observe(&sample(-1.0, 0.0, 20));
+    assert!(detector.is_collision_detected());
+
+    let event = detector.take_collision().unwrap();
+    assert_eq!(event.board_timestamp, Some(20));
+    assert!(event.value.jerk_x < 0.0);
+    assert!(!detector.is_collision_detected());
+    assert_eq!(detector.take_collision(), None);
+}
+
+/// Every collision is delivered to subscriptions and callbacks, whether or not the flag is taken.
+#[test]
+fn subscribers() {
+    let detector = detector().with_holdoff(Duration::from_millis(0));
+    let subscription = detector.subscribe(Capacity::Unbounded);
+
+    let events = Arc::new(Mutex::new(Vec::<CollisionEvent>::new()));
+    let recorder = events.clone();
+    detector.on_collision(move |event| recorder.lock().push(event.value));
+
+    detector.observe(&sample(0.0, 0.0, 0));
+    detector.observe(&sample(1.0, 0.0, 10));
+    detector.observe(&sample(1.0, 0.0, 20));
+    detector.observe(&sample(1.0, 1.0, 30));
+
+    let received: Vec<_> = subscription.try_iter().map(|x| x.value).collect();
+    assert_eq!(received.len(), 2);
+    assert_eq!(*events.lock(), received);
+    assert!((received[1].direction - 90.0).abs() < 1e-3);
+}
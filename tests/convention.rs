@@ -0,0 +1,189 @@
+//! Checks every axis of each coordinate convention against the physical direction it stands for.
+//! The board frame has X to the right, Y forward and Z up, with yaw and headings increasing
+//! clockwise. Each test starts from a reading in that frame and states where it should end up.
+
+use navx::convention::{Convert, Converted, CoordinateConvention};
+use navx::heading::ContinuousYaw;
+use navx::math::Quaternion;
+use navx::rate::AngularRate;
+use navx::serde::Vector;
+use navx::Request;
+use std::io;
+
+use CoordinateConvention::*;
+
+const CONVENTIONS: [CoordinateConvention; 4] = [Native, Nwu, Enu, Ned];
+const EPSILON: f32 = 1e-5;
+
+fn assert_vector(actual: Vector<f32>, expected: (f32, f32, f32)) {
+    assert!(
+        (actual.x - expected.0).abs() < EPSILON
+            && (actual.y - expected.1).abs() < EPSILON
+            && (actual.z - expected.2).abs() < EPSILON,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+/// A vector pointing out of the front of the board is X in NWU and NED, and stays Y in ENU.
+#[test]
+fn forward_axis() {
+    let forward = Vector::new(0.0, 1.0, 0.0);
+
+    assert_vector(Native.vector(forward), (0.0, 1.0, 0.0));
+    assert_vector(Nwu.vector(forward), (1.0, 0.0, 0.0));
+    assert_vector(Enu.vector(forward), (0.0, 1.0, 0.0));
+    assert_vector(Ned.vector(forward), (1.0, 0.0, 0.0));
+}
+
+/// A vector pointing to the right of the board is negative Y in NWU, X in ENU and Y in NED.
+#[test]
+fn right_axis() {
+    let right = Vector::new(1.0, 0.0, 0.0);
+
+    assert_vector(Native.vector(right), (1.0, 0.0, 0.0));
+    assert_vector(Nwu.vector(right), (0.0, -1.0, 0.0));
+    assert_vector(Enu.vector(right), (1.0, 0.0, 0.0));
+    assert_vector(Ned.vector(right), (0.0, 1.0, 0.0));
+}
+
+/// Gravity as measured by a board lying flat points up, which is negative Z in NED.
+#[test]
+fn up_axis() {
+    let up = Vector::new(0.0, 0.0, 1.0);
+
+    assert_vector(Native.vector(up), (0.0, 0.0, 1.0));
+    assert_vector(Nwu.vector(up), (0.0, 0.0, 1.0));
+    assert_vector(Enu.vector(up), (0.0, 0.0, 1.0));
+    assert_vector(Ned.vector(up), (0.0, 0.0, -1.0));
+}
+
+/// Raw readings convert the same way, saturating rather than overflowing when negated.
+#[test]
+fn raw_vectors_saturate() {
+    let raw = Vector::new(i16::MIN, 2, i16::MIN).convert(Nwu);
+    assert_eq!((raw.x, raw.y, raw.z), (2, i16::MAX, i16::MIN));
+
+    let raw = Vector::new(1, 2, i16::MIN).convert(Ned);
+    assert_eq!((raw.x, raw.y, raw.z), (2, 1, i16::MAX));
+}
+
+/// Turning 30 degrees clockwise is reported as 30 by the board. Counterclockwise conventions
+/// report -30.
+#[test]
+fn yaw_sign() {
+    assert_eq!(Native.yaw(30.0), 30.0);
+    assert_eq!(Nwu.yaw(30.0), -30.0);
+    assert_eq!(Enu.yaw(30.0), -30.0);
+    assert_eq!(Ned.yaw(30.0), 30.0);
+
+    for convention in &CONVENTIONS {
+        assert_eq!(convention.is_clockwise(), convention.yaw(30.0) > 0.0);
+    }
+}
+
+/// Negating yaw keeps it in [-180, 180), so facing backwards stays at -180.
+#[test]
+fn yaw_range() {
+    assert_eq!(Nwu.yaw(-180.0), -180.0);
+    assert_eq!(Nwu.yaw(179.0), -179.0);
+    assert_eq!(Nwu.yaw(-179.0), 179.0);
+}
+
+/// A continuous angle keeps counting turns in the new direction.
+#[test]
+fn continuous_yaw() {
+    let yaw = ContinuousYaw::from_angle(370.0);
+
+    assert_eq!(yaw.convert(Ned).angle, 370.0);
+
+    let converted = yaw.convert(Nwu);
+    assert_eq!(converted.angle, -370.0);
+    assert_eq!(converted.turns, -1);
+    assert!((converted.yaw + 10.0).abs() < EPSILON);
+}
+
+/// Facing east is a compass heading of 90 clockwise from north, or 270 counterclockwise.
+#[test]
+fn heading_sign() {
+    assert_eq!(Native.heading(90.0), 90.0);
+    assert_eq!(Nwu.heading(90.0), 270.0);
+    assert_eq!(Enu.heading(90.0), 270.0);
+    assert_eq!(Ned.heading(90.0), 90.0);
+    assert_eq!(Nwu.heading(0.0), 0.0);
+}
+
+/// Pitching the nose up by 10 degrees is positive everywhere except NWU, whose pitch axis points
+/// left.
+#[test]
+fn pitch_sign() {
+    assert_eq!(Native.pitch(10.0), 10.0);
+    assert_eq!(Nwu.pitch(10.0), -10.0);
+    assert_eq!(Enu.pitch(10.0), 10.0);
+    assert_eq!(Ned.pitch(10.0), 10.0);
+}
+
+/// Rolling the right side down by 10 degrees is positive in every convention.
+#[test]
+fn roll_sign() {
+    for convention in &CONVENTIONS {
+        assert_eq!(convention.roll(10.0), 10.0);
+    }
+}
+
+/// The gyro measures right handed rates, so turning clockwise is a negative rate about the up axis
+/// even when the board is tilted. In every standard convention the vertical rate has the same sign
+/// as yaw, which is only untrue of the board frame.
+#[test]
+fn gyro_matches_yaw() {
+    let orientation = Quaternion::from_yaw_pitch_roll(25.0, 10.0, -5.0);
+    let body = orientation.conjugate().rotate(Vector::new(0.0, 0.0, -90.0));
+    let clockwise = AngularRate::new(body, &orientation);
+
+    assert!((clockwise.yaw_rate - 90.0).abs() < 1e-3);
+
+    for convention in &CONVENTIONS {
+        let rate = clockwise.convert(*convention);
+        let world = convention.orientation(orientation).rotate(rate.body);
+        let vertical = if *convention == Native {
+            -world.z
+        } else {
+            world.z
+        };
+
+        assert!((vertical - convention.yaw_rate(90.0)).abs() < 1e-3);
+        assert!((rate.yaw_rate - convention.yaw_rate(90.0)).abs() < 1e-3);
+    }
+}
+
+/// Converting an orientation is the same as converting the vectors it rotates.
+#[test]
+fn orientation_matches_vectors() {
+    let q = Quaternion::from_yaw_pitch_roll(40.0, -15.0, 70.0);
+    let v = Vector::new(0.3, -0.5, 0.8);
+
+    for convention in &CONVENTIONS {
+        let converted = q.convert(*convention);
+        let expected = convention.vector(q.rotate(v));
+        let actual = converted.rotate(convention.vector(v));
+
+        assert_vector(actual, (expected.x, expected.y, expected.z));
+    }
+}
+
+struct Fixed(Vector<f32>);
+
+impl Request<Vector<f32>> for Fixed {
+    fn read(&mut self) -> io::Result<Vector<f32>> {
+        Ok(self.0)
+    }
+}
+
+/// A converted connection applies the convention to every read.
+#[test]
+fn converted_reads() {
+    let mut io = Converted::new(Fixed(Vector::new(1.0, 2.0, 3.0)), Ned);
+
+    assert_vector(io.read().unwrap(), (2.0, 1.0, -3.0));
+}
@@ -0,0 +1,113 @@
+//! Drives a robot in a straight line and corrects it with a vision measurement that arrives late,
+//! checking that the odometry since the capture is replayed on top of the corrected pose.
+
+use navx::estimator::{PoseEstimator, StdDevs};
+use navx::heading::ContinuousYaw;
+use navx::odometry::*;
+use std::time::{Duration, Instant};
+
+const STEPS: u32 = 50;
+const PERIOD: Duration = Duration::from_millis(20);
+const CAPTURE_STEP: u32 = 25;
+const EPSILON: f64 = 1e-5;
+
+fn assert_pose(actual: Pose, expected: Pose) {
+    assert!(
+        (actual.x - expected.x).abs() < EPSILON
+            && (actual.y - expected.y).abs() < EPSILON
+            && (actual.heading - expected.heading).abs() < EPSILON,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+/// An estimator which has driven 1cm forward every period, trusting odometry and vision equally
+fn drive(start: Instant) -> PoseEstimator<DifferentialDrive> {
+    let yaw = ContinuousYaw::from_angle(0.0);
+    let mut estimator = PoseEstimator::new(DifferentialDrive::new(0.6), yaw, Pose::default());
+    estimator.set_state_std_devs(StdDevs::new(0.1, 0.1, 1.0));
+    let deltas = DifferentialDeltas {
+        left: 0.01,
+        right: 0.01,
+    };
+
+    for i in 1..=STEPS {
+        estimator.update_at(start + PERIOD * i, yaw, &deltas);
+    }
+
+    estimator
+}
+
+/// Half of a position error at the capture time is applied, and the 25cm driven since then is
+/// added on top of it.
+#[test]
+fn delayed_position_is_replayed() {
+    let start = Instant::now();
+    let capture = start + PERIOD * CAPTURE_STEP;
+    let mut estimator = drive(start);
+
+    assert_pose(
+        estimator.pose_at(capture).unwrap(),
+        Pose::new(0.25, 0.0, 0.0),
+    );
+
+    let measured = Pose::new(0.35, 0.1, 0.0);
+    let std_devs = StdDevs::new(0.1, 0.1, 1.0);
+    assert!(estimator.add_vision_measurement_with_std_devs(measured, capture, std_devs));
+
+    assert_pose(
+        estimator.pose_at(capture).unwrap(),
+        Pose::new(0.3, 0.05, 0.0),
+    );
+    assert_pose(
+        estimator.pose_at(capture + PERIOD * 10).unwrap(),
+        Pose::new(0.4, 0.05, 0.0),
+    );
+    assert_pose(estimator.pose(), Pose::new(0.55, 0.05, 0.0));
+}
+
+/// Correcting the heading at the capture time turns the path driven since then with it, and later
+/// odometry continues from the corrected heading.
+#[test]
+fn delayed_heading_is_replayed() {
+    let start = Instant::now();
+    let capture = start + PERIOD * CAPTURE_STEP;
+    let mut estimator = drive(start);
+
+    let measured = Pose::new(0.25, 0.0, 10.0);
+    let std_devs = StdDevs::new(0.1, 0.1, 1.0);
+    assert!(estimator.add_vision_measurement_with_std_devs(measured, capture, std_devs));
+
+    let (sin, cos) = 5f64.to_radians().sin_cos();
+    assert_pose(
+        estimator.pose_at(capture).unwrap(),
+        Pose::new(0.25, 0.0, 5.0),
+    );
+    assert_pose(
+        estimator.pose(),
+        Pose::new(0.25 + 0.25 * cos, 0.25 * sin, 5.0),
+    );
+
+    let deltas = DifferentialDeltas {
+        left: 0.01,
+        right: 0.01,
+    };
+    let yaw = ContinuousYaw::from_angle(0.0);
+    estimator.update_at(start + PERIOD * (STEPS + 1), yaw, &deltas);
+
+    assert_pose(
+        estimator.pose(),
+        Pose::new(0.25 + 0.26 * cos, 0.26 * sin, 5.0),
+    );
+}
+
+/// A measurement captured before the history starts is ignored.
+#[test]
+fn old_measurement_is_ignored() {
+    let start = Instant::now() + Duration::from_secs(1);
+    let mut estimator = drive(start);
+
+    assert!(!estimator.add_vision_measurement(Pose::new(1.0, 1.0, 0.0), start));
+    assert_pose(estimator.pose(), Pose::new(0.5, 0.0, 0.0));
+}
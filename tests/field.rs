@@ -0,0 +1,118 @@
+//! Turns the robot on the field from each alliance and checks the headings and field relative
+//! velocities. The navX yaw increases clockwise while field headings increase counterclockwise.
+
+use navx::field::{Alliance, FieldOrientation};
+use navx::watch::Sample;
+use std::time::Instant;
+
+const EPSILON: f64 = 1e-4;
+
+/// Feed the navX yaw seen after turning `heading` degrees counterclockwise from where the board was
+/// zeroed
+fn turn_to(orientation: &FieldOrientation, heading: f64) {
+    orientation.observe(&Sample {
+        host_time: Instant::now(),
+        board_timestamp: None,
+        value: -heading as f32,
+    });
+}
+
+fn assert_close(actual: Option<f64>, expected: f64) {
+    let actual = actual.expect("no heading");
+    assert!(
+        (actual - expected).abs() < EPSILON,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+fn assert_velocity(actual: (f64, f64), expected: (f64, f64)) {
+    assert!(
+        (actual.0 - expected.0).abs() < EPSILON && (actual.1 - expected.1).abs() < EPSILON,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+/// Nothing is known before the first yaw, so velocities pass through unchanged.
+#[test]
+fn before_first_yaw() {
+    let orientation = FieldOrientation::new(Alliance::Blue);
+    orientation.zero();
+
+    assert_eq!(orientation.field_heading(), None);
+    assert_eq!(orientation.driver_heading(), None);
+    assert_velocity(orientation.field_relative(1.0, 0.5), (1.0, 0.5));
+}
+
+/// Zeroing on the blue alliance makes the robot face along the field heading of zero. Turning a
+/// quarter turn left faces it to the left of the drivers, so pushing away from them drives the
+/// robot to its right.
+#[test]
+fn zero_on_blue() {
+    let orientation = FieldOrientation::new(Alliance::Blue);
+    turn_to(&orientation, -30.0);
+    orientation.zero();
+
+    assert_close(orientation.field_heading(), 0.0);
+    assert_close(orientation.driver_heading(), 0.0);
+    assert_velocity(orientation.field_relative(1.0, 0.0), (1.0, 0.0));
+
+    turn_to(&orientation, 60.0);
+    assert_close(orientation.field_heading(), 90.0);
+    assert_close(orientation.driver_heading(), 90.0);
+    assert_velocity(orientation.field_relative(1.0, 0.0), (0.0, -1.0));
+    assert_velocity(orientation.field_relative(0.0, 1.0), (1.0, 0.0));
+}
+
+/// Zeroing on the red alliance faces the robot down the field towards the blue wall. The same
+/// quarter turn left gives the same driver heading as on blue.
+#[test]
+fn zero_on_red() {
+    let orientation = FieldOrientation::new(Alliance::Red);
+    turn_to(&orientation, -30.0);
+    orientation.zero();
+
+    assert_close(orientation.field_heading(), -180.0);
+    assert_close(orientation.driver_heading(), 0.0);
+
+    turn_to(&orientation, 60.0);
+    assert_close(orientation.field_heading(), -90.0);
+    assert_close(orientation.driver_heading(), 90.0);
+    assert_velocity(orientation.field_relative(1.0, 0.0), (0.0, -1.0));
+}
+
+/// A robot placed facing a field heading of 90 faces left of the blue drivers but right of the red
+/// ones, so the field relative velocity turns the other way.
+#[test]
+fn field_heading_on_both_alliances() {
+    let orientation = FieldOrientation::new(Alliance::Blue);
+    turn_to(&orientation, 10.0);
+    orientation.set_field_heading(90.0);
+
+    assert_close(orientation.field_heading(), 90.0);
+    assert_close(orientation.driver_heading(), 90.0);
+    assert_velocity(orientation.field_relative(1.0, 0.0), (0.0, -1.0));
+
+    orientation.set_alliance(Alliance::Red);
+    assert_close(orientation.field_heading(), 90.0);
+    assert_close(orientation.driver_heading(), -90.0);
+    assert_velocity(orientation.field_relative(1.0, 0.0), (0.0, 1.0));
+
+    turn_to(&orientation, 100.0);
+    assert_close(orientation.field_heading(), -180.0);
+    assert_close(orientation.driver_heading(), 0.0);
+}
+
+/// Flipping a heading to the red drivers and back gives the original heading.
+#[test]
+fn flip_heading() {
+    assert_eq!(Alliance::Blue.flip_heading(90.0), 90.0);
+    assert_eq!(Alliance::Red.flip_heading(90.0), -90.0);
+    assert_eq!(
+        Alliance::Red.flip_heading(Alliance::Red.flip_heading(45.0)),
+        45.0
+    );
+}
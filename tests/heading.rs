@@ -0,0 +1,79 @@
+//! Checks that every yaw source agrees with the clockwise yaw reported by the board.
+
+use navx::heading::*;
+use navx::math::Quaternion;
+
+const EPSILON: f32 = 1e-4;
+
+/// A quaternion turned 30 degrees counterclockwise about the vertical axis, which the board
+/// reports as a yaw of -30.
+#[test]
+fn quaternion_yaw_is_clockwise() {
+    let q = Quaternion::from_yaw_pitch_roll(30.0, 0.0, 0.0);
+    assert!((q.yaw_degrees() + 30.0).abs() < EPSILON);
+
+    let q = Quaternion::from_board_angles(45.0, 10.0, -5.0);
+    assert!((q.yaw_degrees() - 45.0).abs() < EPSILON);
+}
+
+/// Tilting the board does not change the yaw, which matches the angles the board reports.
+#[test]
+fn quaternion_yaw_when_tilted() {
+    for &(pitch, roll) in &[(20.0, 0.0), (0.0, -30.0), (35.0, 25.0), (-60.0, 70.0)] {
+        let q = Quaternion::from_board_angles(-120.0, pitch, roll);
+        assert!(
+            (q.yaw_degrees() + 120.0).abs() < 1e-3,
+            "pitch {}, roll {}: got {}",
+            pitch,
+            roll,
+            q.yaw_degrees()
+        );
+    }
+}
+
+/// Facing backwards stays inside [-180, 180).
+#[test]
+fn quaternion_yaw_range() {
+    let yaw = Quaternion::from_yaw_pitch_roll(180.0, 0.0, 0.0).yaw_degrees();
+    assert!((-180.0..180.0).contains(&yaw));
+    assert!((yaw.abs() - 180.0).abs() < EPSILON);
+}
+
+/// Turning clockwise through quaternions counts up, in the same way as the board yaw.
+#[test]
+fn quaternion_tracks_like_board_yaw() {
+    let mut accumulator = YawAccumulator::new();
+
+    for step in 0..=40 {
+        let clockwise = step as f32 * 10.0;
+        let q = Quaternion::from_yaw_pitch_roll(-clockwise, 0.0, 0.0);
+        accumulator.update(q.yaw_degrees());
+    }
+
+    assert!((accumulator.get().angle - 400.0).abs() < 1e-3);
+}
+
+/// An offset applied to a quaternion moves its yaw the same way as the board yaw.
+#[test]
+fn quaternion_offset_matches_board_yaw() {
+    let mut offset = YawOffset::new();
+    offset.set_adjustment(20.0);
+
+    let q = Quaternion::from_board_angles(30.0, 10.0, -5.0);
+    let adjusted = offset.apply(q);
+    let (_, pitch, roll) = adjusted.to_board_angles();
+
+    assert!((adjusted.yaw_degrees() - offset.yaw(q.yaw_degrees())).abs() < EPSILON);
+    assert!((adjusted.yaw_degrees() - 50.0).abs() < EPSILON);
+    assert!((pitch - 10.0).abs() < EPSILON && (roll + 5.0).abs() < EPSILON);
+}
+
+/// Inverting the offset turns the clockwise quaternion yaw into a counterclockwise one.
+#[test]
+fn quaternion_offset_inverted() {
+    let mut offset = YawOffset::new();
+    offset.set_inverted(true);
+
+    let q = Quaternion::from_yaw_pitch_roll(-30.0, 0.0, 0.0);
+    assert!((offset.apply(q).yaw_degrees() + 30.0).abs() < EPSILON);
+}
@@ -0,0 +1,162 @@
+//! Feeds a health monitor samples, statuses and errors at known times and checks every state it
+//! moves through, including going stale without anything being fed.
+
+use navx::health::{Health, HealthMonitor, StatusSource};
+use navx::serde::{CalibrationStatus, OperationStatus};
+use parking_lot::Mutex;
+use std::io::{self, ErrorKind};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use Health::*;
+
+struct Status(OperationStatus, CalibrationStatus);
+
+impl StatusSource for Status {
+    fn operation_status(&self) -> OperationStatus {
+        self.0
+    }
+
+    fn calibration_status(&self) -> CalibrationStatus {
+        self.1
+    }
+}
+
+fn ms(start: Instant, ms: u64) -> Instant {
+    start + Duration::from_millis(ms)
+}
+
+fn timed_out() -> io::Error {
+    io::Error::new(ErrorKind::TimedOut, "no response")
+}
+
+/// Record every transition of a monitor.
+fn transitions(monitor: &HealthMonitor) -> Arc<Mutex<Vec<(Health, Health)>>> {
+    let transitions = Arc::new(Mutex::new(Vec::new()));
+    let recorder = transitions.clone();
+    monitor.on_transition(move |old, new| recorder.lock().push((old, new)));
+    transitions
+}
+
+/// The board reports its startup, then calibrates before it is ready.
+#[test]
+fn startup() {
+    let monitor = HealthMonitor::default();
+    let transitions = transitions(&monitor);
+    assert_eq!(monitor.health(), Health::Connecting);
+    assert!(!monitor.is_connected());
+
+    monitor.observe_sample(None, Instant::now());
+    monitor.observe_status(&Status(
+        OperationStatus::Initializing,
+        CalibrationStatus::empty(),
+    ));
+    assert!(monitor.is_calibrating());
+
+    monitor.observe_status(&Status(
+        OperationStatus::Calibrating,
+        CalibrationStatus::empty(),
+    ));
+    monitor.observe_status(&Status(
+        OperationStatus::Normal,
+        CalibrationStatus::IMU_ACCUMULATE,
+    ));
+    assert_eq!(monitor.health(), Health::Calibrating);
+
+    monitor.observe_status(&Status(
+        OperationStatus::Normal,
+        CalibrationStatus::IMU_COMPLETE | CalibrationStatus::MAG_COMPLETE,
+    ));
+    assert_eq!(monitor.health(), Health::Ready);
+    assert!(!monitor.is_calibrating());
+
+    monitor.observe_status(&Status(
+        OperationStatus::Error,
+        CalibrationStatus::IMU_COMPLETE,
+    ));
+    assert_eq!(monitor.health(), Health::Degraded);
+
+    assert_eq!(
+        *transitions.lock(),
+        vec![
+            (Connecting, Ready),
+            (Ready, Initializing),
+            (Initializing, Calibrating),
+            (Calibrating, Ready),
+            (Ready, Degraded),
+        ]
+    );
+}
+
+/// Failed reads degrade the connection until enough of them in a row disconnect it. A sample
+/// brings it back.
+#[test]
+fn errors() {
+    let monitor = HealthMonitor::default().with_disconnect_errors(3);
+    let now = Instant::now();
+    monitor.observe_sample(None, now);
+
+    monitor.observe_error(&io::Error::from(ErrorKind::Interrupted));
+    assert_eq!(monitor.update(now), Health::Ready);
+
+    monitor.observe_error(&timed_out());
+    monitor.observe_error(&timed_out());
+    assert_eq!(monitor.update(now), Health::Degraded);
+
+    monitor.observe_error(&timed_out());
+    assert_eq!(monitor.update(now), Health::Disconnected);
+
+    monitor.observe_sample(None, now);
+    assert_eq!(monitor.update(now), Health::Ready);
+}
+
+/// Data goes stale once nothing fresh has arrived within the timeout. A repeated board timestamp is
+/// not fresh.
+#[test]
+fn stale() {
+    let monitor = HealthMonitor::new(Duration::from_millis(100));
+    let transitions = transitions(&monitor);
+    let start = Instant::now();
+
+    monitor.observe_sample(Some(1), start);
+    monitor.observe_sample(Some(1), ms(start, 80));
+    assert_eq!(monitor.update(ms(start, 100)), Health::Ready);
+    assert_eq!(monitor.update(ms(start, 101)), Health::Disconnected);
+
+    monitor.observe_sample(Some(2), ms(start, 150));
+    assert_eq!(monitor.update(ms(start, 240)), Health::Ready);
+    assert_eq!(
+        *transitions.lock(),
+        vec![
+            (Connecting, Ready),
+            (Ready, Disconnected),
+            (Disconnected, Ready),
+        ]
+    );
+}
+
+/// Nothing notices the data going stale until the monitor is queried.
+#[test]
+fn is_connected_times_out() {
+    let monitor = HealthMonitor::new(Duration::from_millis(30));
+    let transitions = transitions(&monitor);
+
+    monitor.observe_sample(None, Instant::now());
+    assert!(monitor.is_connected());
+    assert!(monitor.time_since_fresh().unwrap() < Duration::from_millis(30));
+
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(transitions.lock().len(), 1);
+
+    assert!(!monitor.is_connected());
+    assert_eq!(
+        transitions.lock().last(),
+        Some(&(Health::Ready, Health::Disconnected))
+    );
+
+    // A longer timeout makes the same data fresh again
+    monitor.set_stale_timeout(Duration::from_secs(10));
+    assert!(monitor.is_connected());
+    assert_eq!(monitor.health(), Health::Ready);
+}
@@ -0,0 +1,166 @@
+//! Looks up values in a history of samples read 10ms apart, between and around the samples, and
+//! checks which samples are evicted.
+
+use navx::history::{interpolate_degrees, History, Interpolate};
+use navx::math::Quaternion;
+use navx::serde::Vector;
+use navx::watch::Sample;
+use std::time::{Duration, Instant};
+
+const EPSILON: f32 = 1e-4;
+
+fn ms(start: Instant, ms: u64) -> Instant {
+    start + Duration::from_millis(ms)
+}
+
+fn sample<T>(start: Instant, time: u64, value: T) -> Sample<T> {
+    Sample {
+        host_time: ms(start, time),
+        board_timestamp: None,
+        value,
+    }
+}
+
+/// Samples of 0, 1, 2, ... read at 0, 10, 20, ... ms
+fn counting(start: Instant, len: u64, capacity: usize) -> History<f32> {
+    let mut history = History::new(capacity);
+    for i in 0..len {
+        history.push(sample(start, i * 10, i as f32));
+    }
+    history
+}
+
+fn values<'a>(samples: impl Iterator<Item = &'a Sample<f32>>) -> Vec<f32> {
+    samples.map(|x| x.value).collect()
+}
+
+#[test]
+fn at() {
+    let start = Instant::now();
+    let history = counting(start, 5, 10);
+
+    assert_eq!(history.at(ms(start, 0)), Some(0.0));
+    assert_eq!(history.at(ms(start, 20)), Some(2.0));
+    assert!((history.at(ms(start, 25)).unwrap() - 2.5).abs() < EPSILON);
+    assert!((history.at(ms(start, 33)).unwrap() - 3.3).abs() < EPSILON);
+
+    // Newer than every sample gives the latest value, older than every sample gives nothing
+    assert_eq!(history.at(ms(start, 100)), Some(4.0));
+    assert_eq!(History::<f32>::new(4).at(start), None);
+
+    let later = counting(start + Duration::from_millis(1), 5, 10);
+    assert_eq!(later.at(start), None);
+}
+
+#[test]
+fn latest_before() {
+    let start = Instant::now();
+    let history = counting(start, 5, 10);
+
+    assert_eq!(history.latest_before(ms(start, 20)).unwrap().value, 2.0);
+    assert_eq!(history.latest_before(ms(start, 29)).unwrap().value, 2.0);
+    assert_eq!(history.latest_before(ms(start, 500)).unwrap().value, 4.0);
+
+    let later = counting(start + Duration::from_millis(1), 5, 10);
+    assert!(later.latest_before(start).is_none());
+}
+
+/// Both ends of the range are included.
+#[test]
+fn between() {
+    let start = Instant::now();
+    let history = counting(start, 5, 10);
+
+    assert_eq!(
+        values(history.between(ms(start, 10), ms(start, 30))),
+        vec![1.0, 2.0, 3.0]
+    );
+    assert_eq!(
+        values(history.between(ms(start, 11), ms(start, 29))),
+        vec![2.0]
+    );
+    assert_eq!(
+        values(history.between(ms(start, 0), ms(start, 1000))),
+        vec![0.0, 1.0, 2.0, 3.0, 4.0]
+    );
+    assert!(values(history.between(ms(start, 12), ms(start, 18))).is_empty());
+    assert!(values(history.between(ms(start, 30), ms(start, 10))).is_empty());
+}
+
+/// Once full, each new sample evicts the oldest one.
+#[test]
+fn evicts_oldest() {
+    let start = Instant::now();
+    let history = counting(start, 8, 3);
+
+    assert_eq!(history.len(), 3);
+    assert_eq!(history.capacity(), 3);
+    assert_eq!(values(history.iter()), vec![5.0, 6.0, 7.0]);
+    assert_eq!(history.oldest().unwrap().value, 5.0);
+    assert_eq!(history.latest().unwrap().value, 7.0);
+
+    // A capacity of zero still keeps the latest sample
+    let history = counting(start, 3, 0);
+    assert_eq!(values(history.iter()), vec![2.0]);
+}
+
+/// Samples older than the maximum age relative to the newest sample are evicted.
+#[test]
+fn evicts_by_age() {
+    let start = Instant::now();
+    let mut history = History::new(100).with_max_age(Duration::from_millis(25));
+
+    for i in 0..6 {
+        history.push(sample(start, i * 10, i as f32));
+    }
+
+    assert_eq!(values(history.iter()), vec![3.0, 4.0, 5.0]);
+}
+
+/// Samples older than the newest one are dropped to keep the history ordered.
+#[test]
+fn ignores_out_of_order() {
+    let start = Instant::now();
+    let mut history = counting(start, 3, 10);
+    history.push(sample(start, 5, 100.0));
+
+    assert_eq!(values(history.iter()), vec![0.0, 1.0, 2.0]);
+
+    history.clear();
+    assert!(history.is_empty());
+}
+
+/// Orientations are blended along the shortest rotation between them.
+#[test]
+fn slerp() {
+    let start = Instant::now();
+    let mut history = History::new(4);
+    history.push(sample(
+        start,
+        0,
+        Quaternion::from_yaw_pitch_roll(170.0, 0.0, 0.0),
+    ));
+    history.push(sample(
+        start,
+        10,
+        Quaternion::from_yaw_pitch_roll(-170.0, 0.0, 0.0),
+    ));
+
+    let (yaw, pitch, roll) = history.at(ms(start, 5)).unwrap().to_yaw_pitch_roll();
+    assert!((yaw.abs() - 180.0).abs() < 1e-3, "{}", yaw);
+    assert!(pitch.abs() < 1e-3 && roll.abs() < 1e-3);
+
+    let (yaw, _, _) = history.at(ms(start, 2)).unwrap().to_yaw_pitch_roll();
+    assert!((yaw - 174.0).abs() < 1e-3, "{}", yaw);
+}
+
+#[test]
+fn interpolate() {
+    let a = Vector::new(0.0f32, 10.0, -2.0);
+    let b = Vector::new(1.0f32, 20.0, 2.0);
+    assert_eq!(a.interpolate(&b, 0.5), Vector::new(0.5, 15.0, 0.0));
+
+    assert!((interpolate_degrees(170.0, -170.0, 0.25, -180.0) - 175.0).abs() < EPSILON);
+    assert!((interpolate_degrees(170.0, -170.0, 0.75, -180.0) + 175.0).abs() < EPSILON);
+    assert!((interpolate_degrees(350.0, 10.0, 0.75, 0.0) - 5.0).abs() < EPSILON);
+}
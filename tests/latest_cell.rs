@@ -0,0 +1,111 @@
+//! Hammers a `LatestCell` from one writer and several readers to make sure a read never observes
+//! half of one value and half of another, or a value older than the version it was read after.
+
+use navx::cell::LatestCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const READERS: usize = 4;
+const RUN_TIME: Duration = Duration::from_millis(500);
+
+/// Large enough that copying it out is not a single instruction
+type Wide = [u64; 16];
+
+#[test]
+fn no_torn_reads_under_contention() {
+    let cell = Arc::new(LatestCell::<Wide>::new());
+    let done = Arc::new(AtomicBool::new(false));
+
+    let readers: Vec<_> = (0..READERS)
+        .map(|_| {
+            let cell = cell.clone();
+            let done = done.clone();
+
+            thread::spawn(move || {
+                let mut last = 0;
+
+                while !done.load(Ordering::Relaxed) {
+                    let version = cell.version() as u64;
+
+                    if let Some(value) = cell.load() {
+                        assert!(
+                            value.iter().all(|&x| x == value[0]),
+                            "torn read: {:?}",
+                            value
+                        );
+                        assert!(value[0] >= last, "went back from {} to {}", last, value[0]);
+                        assert!(value[0] >= version, "older than version {}", version);
+                        last = value[0];
+                    }
+                }
+            })
+        })
+        .collect();
+
+    let start = Instant::now();
+    let mut writes = 0u64;
+
+    while start.elapsed() < RUN_TIME {
+        writes += 1;
+        cell.store([writes; 16]);
+    }
+
+    done.store(true, Ordering::Relaxed);
+    for reader in readers {
+        reader.join().unwrap();
+    }
+
+    assert_eq!(cell.load(), Some([writes; 16]));
+    assert_eq!(cell.version(), writes as usize);
+}
+
+#[test]
+fn empty_until_first_store() {
+    let cell = LatestCell::<u32>::new();
+    assert_eq!(cell.load(), None);
+    assert_eq!(cell.version(), 0);
+
+    cell.store(7);
+    assert_eq!(cell.load(), Some(7));
+}
+
+#[test]
+fn drops_values_it_holds() {
+    let value = Arc::new(());
+    let cell = LatestCell::new();
+
+    for _ in 0..10 {
+        cell.store(value.clone());
+    }
+    assert_eq!(Arc::strong_count(&value), 5);
+
+    drop(cell);
+    assert_eq!(Arc::strong_count(&value), 1);
+}
+
+/// Writers from several threads wait on each other without losing a value.
+#[test]
+fn concurrent_writers() {
+    let cell = Arc::new(LatestCell::<Wide>::new());
+
+    let writers: Vec<_> = (0..READERS as u64)
+        .map(|i| {
+            let cell = cell.clone();
+            thread::spawn(move || {
+                for _ in 0..10_000 {
+                    cell.store([i; 16]);
+                    let value = cell.load().unwrap();
+                    assert!(value.iter().all(|&x| x == value[0]));
+                }
+            })
+        })
+        .collect();
+
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    assert_eq!(cell.version(), READERS * 10_000);
+}
@@ -0,0 +1,268 @@
+//! Checks the quaternion math against rotations whose result is known by hand, mostly quarter turns
+//! about a single axis.
+
+use navx::math::Quaternion;
+use navx::serde::Vector;
+use navx::FromBuffer;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+const EPSILON: f32 = 1e-5;
+
+const X: Vector<f32> = Vector {
+    x: 1.0,
+    y: 0.0,
+    z: 0.0,
+};
+const Y: Vector<f32> = Vector {
+    x: 0.0,
+    y: 1.0,
+    z: 0.0,
+};
+const Z: Vector<f32> = Vector {
+    x: 0.0,
+    y: 0.0,
+    z: 1.0,
+};
+
+fn assert_vector(actual: Vector<f32>, expected: Vector<f32>) {
+    assert!(
+        (actual - expected).norm() < EPSILON,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+/// Quaternions are equal as rotations if they are equal up to sign.
+fn assert_rotation(actual: Quaternion, expected: Quaternion) {
+    assert!(
+        (actual.dot(&expected).abs() - 1.0).abs() < EPSILON,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+fn assert_angles(actual: (f32, f32, f32), expected: (f32, f32, f32)) {
+    assert!(
+        (actual.0 - expected.0).abs() < 1e-3
+            && (actual.1 - expected.1).abs() < 1e-3
+            && (actual.2 - expected.2).abs() < 1e-3,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+/// A quarter turn counterclockwise about Z
+fn quarter_turn() -> Quaternion {
+    Quaternion::from_axis_angle(Z, FRAC_PI_2)
+}
+
+/// The board sends each component as a little endian Q2.14 fixed point number in w, x, y, z order.
+#[test]
+fn q214_decode() {
+    let q = Quaternion::read(&[0x00, 0x40, 0x00, 0xE0, 0xFF, 0x7F, 0x00, 0x80]);
+
+    assert_eq!(q.w, 1.0);
+    assert_eq!(q.x, -0.5);
+    assert!((q.y - 2.0).abs() < 1e-4);
+    assert_eq!(q.z, -2.0);
+}
+
+#[test]
+fn from_axis_angle() {
+    let half = FRAC_PI_4.cos();
+    assert_rotation(quarter_turn(), Quaternion::new(half, 0.0, 0.0, half));
+
+    // The axis does not have to be normalized
+    assert_rotation(
+        Quaternion::from_axis_angle(Z * 3.0, FRAC_PI_2),
+        quarter_turn(),
+    );
+}
+
+#[test]
+fn normalize() {
+    assert_eq!(
+        Quaternion::new(2.0, 2.0, 2.0, 2.0).normalize(),
+        Quaternion::new(0.5, 0.5, 0.5, 0.5)
+    );
+    assert_eq!(
+        Quaternion::new(0.0, 0.0, 0.0, 0.0).normalize(),
+        Quaternion::IDENTITY
+    );
+
+    // A scaled quaternion rotates the same way as the unit one
+    let q = quarter_turn();
+    let scaled = Quaternion::new(q.w * 3.0, q.x * 3.0, q.y * 3.0, q.z * 3.0);
+    assert_vector(scaled.rotate(X), Y);
+}
+
+/// The conjugate undoes the rotation.
+#[test]
+fn conjugate() {
+    let q = Quaternion::from_yaw_pitch_roll(30.0, -20.0, 45.0);
+
+    assert_eq!(q.conjugate(), Quaternion::new(q.w, -q.x, -q.y, -q.z));
+    assert_rotation(q * q.conjugate(), Quaternion::IDENTITY);
+    assert_vector(q.conjugate().rotate(q.rotate(X)), X);
+    assert_vector(quarter_turn().conjugate().rotate(Y), X);
+}
+
+/// `a * b` rotates by `b` first and then by `a`.
+#[test]
+fn mul() {
+    let i = Quaternion::new(0.0, 1.0, 0.0, 0.0);
+    let j = Quaternion::new(0.0, 0.0, 1.0, 0.0);
+    assert_eq!(i * j, Quaternion::new(0.0, 0.0, 0.0, 1.0));
+    assert_eq!(j * i, Quaternion::new(0.0, 0.0, 0.0, -1.0));
+
+    let about_x = Quaternion::from_axis_angle(X, FRAC_PI_2);
+    let both = quarter_turn() * about_x;
+
+    // Y is turned up to Z about X, and Z is unaffected by the turn about Z
+    assert_vector(both.rotate(Y), Z);
+    assert_vector(both.rotate(X), Y);
+    assert_rotation(
+        quarter_turn() * quarter_turn(),
+        Quaternion::from_axis_angle(Z, PI),
+    );
+}
+
+#[test]
+fn slerp() {
+    let q = quarter_turn();
+
+    assert_rotation(Quaternion::IDENTITY.slerp(&q, 0.0), Quaternion::IDENTITY);
+    assert_rotation(Quaternion::IDENTITY.slerp(&q, 1.0), q);
+    assert_rotation(
+        Quaternion::IDENTITY.slerp(&q, 0.5),
+        Quaternion::from_axis_angle(Z, FRAC_PI_4),
+    );
+}
+
+/// A quaternion and its negation are the same rotation, and a three quarter turn is a quarter turn
+/// the other way, so both interpolate along the short path.
+#[test]
+fn slerp_short_path() {
+    let q = quarter_turn();
+    let negated = Quaternion::new(-q.w, -q.x, -q.y, -q.z);
+    let long = Quaternion::from_axis_angle(Z, 3.0 * FRAC_PI_2);
+
+    assert_rotation(
+        Quaternion::IDENTITY.slerp(&negated, 0.5),
+        Quaternion::from_axis_angle(Z, FRAC_PI_4),
+    );
+    assert_rotation(
+        Quaternion::IDENTITY.slerp(&long, 0.5),
+        Quaternion::from_axis_angle(Z, -FRAC_PI_4),
+    );
+}
+
+/// Nearly parallel rotations still interpolate to a unit quaternion between them.
+#[test]
+fn slerp_nearly_parallel() {
+    let angle = 1e-4;
+    let q = Quaternion::from_axis_angle(Z, angle);
+    let half = Quaternion::IDENTITY.slerp(&q, 0.5);
+
+    assert!((half.norm() - 1.0).abs() < EPSILON);
+    assert_rotation(half, Quaternion::from_axis_angle(Z, angle / 2.0));
+    assert_rotation(q.slerp(&q, 0.3), q);
+}
+
+#[test]
+fn euler_angles() {
+    assert_rotation(
+        Quaternion::from_yaw_pitch_roll(90.0, 0.0, 0.0),
+        quarter_turn(),
+    );
+    assert_rotation(
+        Quaternion::from_yaw_pitch_roll(0.0, 90.0, 0.0),
+        Quaternion::from_axis_angle(Y, FRAC_PI_2),
+    );
+    assert_rotation(
+        Quaternion::from_yaw_pitch_roll(0.0, 0.0, 90.0),
+        Quaternion::from_axis_angle(X, FRAC_PI_2),
+    );
+
+    // Yaw is applied last
+    assert_rotation(
+        Quaternion::from_yaw_pitch_roll(90.0, 0.0, 90.0),
+        quarter_turn() * Quaternion::from_axis_angle(X, FRAC_PI_2),
+    );
+}
+
+#[test]
+fn euler_round_trip() {
+    for &angles in &[
+        (0.0, 0.0, 0.0),
+        (30.0, 20.0, -40.0),
+        (-170.0, -80.0, 150.0),
+        (120.0, 45.0, -179.0),
+    ] {
+        let q = Quaternion::from_yaw_pitch_roll(angles.0, angles.1, angles.2);
+        assert_angles(q.to_yaw_pitch_roll(), angles);
+
+        let q = Quaternion::from_board_angles(angles.0, angles.1, angles.2);
+        assert_angles(q.to_board_angles(), angles);
+    }
+}
+
+/// The board yaw is clockwise, pitch raises the nose (+Y) and roll lowers the right side (+X).
+#[test]
+fn board_angles() {
+    assert_vector(Quaternion::from_board_angles(90.0, 0.0, 0.0).rotate(Y), X);
+    assert_vector(Quaternion::from_board_angles(0.0, 90.0, 0.0).rotate(Y), Z);
+    assert_vector(Quaternion::from_board_angles(0.0, 0.0, 90.0).rotate(X), -Z);
+}
+
+#[test]
+fn rotation_matrix() {
+    let m = quarter_turn().rotation_matrix();
+    let expected = [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]];
+
+    for (row, expected) in m.iter().zip(&expected) {
+        for (x, expected) in row.iter().zip(expected) {
+            assert!((x - expected).abs() < EPSILON, "got {:?}", m);
+        }
+    }
+
+    // Multiplying by the matrix matches rotating by the quaternion
+    let q = Quaternion::from_yaw_pitch_roll(-60.0, 25.0, 110.0);
+    let m = q.rotation_matrix();
+    let v = Vector::new(0.3, -1.2, 2.0);
+    let product = Vector::new(
+        m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+        m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+        m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+    );
+    assert_vector(product, q.rotate(v));
+}
+
+#[test]
+fn rotate() {
+    let q = quarter_turn();
+
+    assert_vector(q.rotate(X), Y);
+    assert_vector(q.rotate(Y), -X);
+    assert_vector(q.rotate(Z), Z);
+    assert_vector(Quaternion::from_axis_angle(X, FRAC_PI_2).rotate(Y), Z);
+    assert_vector(Quaternion::from_axis_angle(Y, FRAC_PI_2).rotate(Z), X);
+    assert_vector(
+        Quaternion::IDENTITY.rotate(Vector::new(1.0, 2.0, 3.0)),
+        Vector::new(1.0, 2.0, 3.0),
+    );
+}
+
+/// Gravity points down the body Z axis of a flat board, and along body Y once the nose is raised a
+/// quarter turn.
+#[test]
+fn gravity() {
+    assert_vector(Quaternion::IDENTITY.gravity(), Z);
+    assert_vector(Quaternion::from_board_angles(0.0, 90.0, 0.0).gravity(), Y);
+
+    let accel = Quaternion::from_board_angles(0.0, 20.0, -35.0).gravity();
+    assert_vector(Quaternion::level(accel).rotate(accel), Z);
+}
@@ -0,0 +1,156 @@
+//! Feeds the motion detector synthetic samples from the board flags and from the host thresholds,
+//! and checks when it considers the robot moving, rotating and stationary.
+
+use navx::motion::{MotionDetector, MotionEvent, MotionThresholds};
+use navx::serde::{SensorStatus, Vector};
+use navx::watch::Sample;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A sample taken `ms` milliseconds into the test
+fn sample<T>(value: T, ms: u32) -> Sample<T> {
+    Sample {
+        host_time: Instant::now(),
+        board_timestamp: Some(ms),
+        value,
+    }
+}
+
+fn accel(g: f32) -> Vector<f32> {
+    Vector::new(g, 0.0, 0.0)
+}
+
+/// Record every event published by a detector.
+fn events(detector: &MotionDetector) -> Arc<Mutex<Vec<MotionEvent>>> {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorder = events.clone();
+    detector.on_event(move |event| recorder.lock().push(event.value));
+    events
+}
+
+/// Nothing is known until the first sample.
+#[test]
+fn nothing_observed() {
+    let detector = MotionDetector::default();
+
+    assert!(!detector.is_moving());
+    assert!(!detector.is_rotating());
+    assert!(!detector.is_yaw_stable());
+    assert!(!detector.is_stationary());
+}
+
+/// The board flags alone decide the state when nothing else is observed.
+#[test]
+fn board_flags() {
+    let detector = MotionDetector::default();
+
+    detector.observe_status(&sample(SensorStatus::YAW_STABLE, 0));
+    assert!(!detector.is_moving());
+    assert!(!detector.is_rotating());
+    assert!(detector.is_yaw_stable());
+    assert!(detector.is_stationary());
+
+    detector.observe_status(&sample(SensorStatus::MOVING | SensorStatus::YAW_STABLE, 10));
+    assert!(detector.is_moving());
+    assert!(!detector.is_rotating());
+    assert!(!detector.is_stationary());
+
+    detector.observe_status(&sample(SensorStatus::empty(), 20));
+    assert!(!detector.is_moving());
+    assert!(detector.is_rotating());
+    assert!(!detector.is_yaw_stable());
+}
+
+/// Acceleration starts motion above the moving threshold, and only stops it after staying below
+/// the stopped threshold for the settle time. Values in between keep the current state.
+#[test]
+fn accel_hysteresis() {
+    let detector = MotionDetector::default();
+    let thresholds = MotionThresholds::default();
+    let settle = thresholds.settle_time.as_millis() as u32;
+
+    detector.observe_accel(&sample(accel(0.015), 0));
+    assert!(!detector.is_moving());
+
+    detector.observe_accel(&sample(accel(0.03), 10));
+    assert!(detector.is_moving());
+
+    detector.observe_accel(&sample(accel(0.015), 20));
+    assert!(detector.is_moving());
+
+    detector.observe_accel(&sample(accel(0.005), 30));
+    assert!(detector.is_moving());
+
+    detector.observe_accel(&sample(accel(0.005), 20 + settle - 1));
+    assert!(detector.is_moving());
+
+    detector.observe_accel(&sample(accel(0.005), 20 + settle));
+    assert!(!detector.is_moving());
+    assert!(detector.is_stationary());
+}
+
+/// A rate noisy around the stopped threshold never settles, so the robot keeps rotating.
+#[test]
+fn rate_hysteresis() {
+    let detector = MotionDetector::default();
+
+    detector.observe_rate(&sample(1.5f32, 0));
+    assert!(!detector.is_rotating());
+    assert!(detector.is_yaw_stable());
+
+    detector.observe_rate(&sample(-3.0f32, 10));
+    assert!(detector.is_rotating());
+
+    for i in 0..100 {
+        let rate = if i % 2 == 0 { 0.5f32 } else { 1.5 };
+        detector.observe_rate(&sample(rate, 20 + i * 10));
+    }
+    assert!(detector.is_rotating());
+}
+
+/// Events are only published when the robot changes between moving and stationary.
+#[test]
+fn stationary_events() {
+    let detector = MotionDetector::default();
+    let events = events(&detector);
+
+    detector.observe_rate(&sample(0.0f32, 0));
+    detector.observe_rate(&sample(0.0f32, 10));
+    detector.observe_rate(&sample(5.0f32, 20));
+    detector.observe_rate(&sample(4.0f32, 30));
+    detector.observe_rate(&sample(0.0f32, 40));
+    detector.observe_rate(&sample(0.0f32, 300));
+
+    assert_eq!(
+        *events.lock(),
+        vec![
+            MotionEvent::Stationary,
+            MotionEvent::Moving,
+            MotionEvent::Stationary
+        ]
+    );
+
+    detector.observe_status(&sample(
+        SensorStatus::MOVING | SensorStatus::YAW_STABLE,
+        310,
+    ));
+    assert_eq!(events.lock().last(), Some(&MotionEvent::Moving));
+}
+
+/// Resetting forgets every sample, so the next one publishes an event again.
+#[test]
+fn reset() {
+    let detector = MotionDetector::default();
+    let events = events(&detector);
+
+    detector.observe_status(&sample(SensorStatus::YAW_STABLE, 0));
+    detector.reset();
+    assert!(!detector.is_stationary());
+
+    detector.observe_status(&sample(SensorStatus::YAW_STABLE, 10));
+    assert_eq!(
+        *events.lock(),
+        vec![MotionEvent::Stationary, MotionEvent::Stationary]
+    );
+}
@@ -0,0 +1,147 @@
+//! Checks a board mounted at an angle against the physical motion of the robot. Angles are in the
+//! convention of the board: yaw clockwise, pitch about X with the nose up and roll about Y with the
+//! right side down.
+
+use navx::math::Quaternion;
+use navx::mount::{MountingRotation, ToRobotFrame};
+use navx::register::storage::Orientation;
+use navx::serde::Vector;
+
+const EPSILON: f32 = 1e-3;
+
+fn assert_vector(actual: Vector<f32>, expected: (f32, f32, f32)) {
+    assert!(
+        (actual.x - expected.0).abs() < EPSILON
+            && (actual.y - expected.1).abs() < EPSILON
+            && (actual.z - expected.2).abs() < EPSILON,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+/// Angles are compared across the wrap, so a heading just below 360 is close to zero.
+fn close(a: f32, b: f32) -> bool {
+    ((a - b + 180.0).rem_euclid(360.0) - 180.0).abs() < EPSILON
+}
+
+fn assert_angles(actual: (f32, f32, f32), expected: (f32, f32, f32)) {
+    assert!(
+        close(actual.0, expected.0) && close(actual.1, expected.1) && close(actual.2, expected.2),
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+/// The orientation the board reports when the robot has the orientation given by `robot`.
+fn board_angles(mounting: &MountingRotation, robot: (f32, f32, f32)) -> (f32, f32, f32) {
+    let robot = Quaternion::from_board_angles(robot.0, robot.1, robot.2);
+    (robot * mounting.rotation()).to_board_angles()
+}
+
+/// Yaw turns the front of the board towards its right, pitch raises the front and roll lowers the
+/// right side.
+#[test]
+fn board_angle_signs() {
+    let forward = Vector::new(0.0, 1.0, 0.0);
+    let right = Vector::new(1.0, 0.0, 0.0);
+
+    assert!(
+        Quaternion::from_board_angles(30.0, 0.0, 0.0)
+            .rotate(forward)
+            .x
+            > 0.0
+    );
+    assert!(
+        Quaternion::from_board_angles(0.0, 10.0, 0.0)
+            .rotate(forward)
+            .z
+            > 0.0
+    );
+    assert!(
+        Quaternion::from_board_angles(0.0, 0.0, 10.0)
+            .rotate(right)
+            .z
+            < 0.0
+    );
+
+    let angles = (-120.0, 20.0, -35.0);
+    let q = Quaternion::from_board_angles(angles.0, angles.1, angles.2);
+    assert_angles(q.to_board_angles(), angles);
+}
+
+/// A board turned 30 degrees clockwise on a turret plate sees the front of the robot 30 degrees to
+/// its left. Yaw and both headings read 30 degrees less for the robot.
+#[test]
+fn yawed_turret_mount() {
+    let mounting = MountingRotation::from_yaw_pitch_roll(30.0, 0.0, 0.0);
+    let (sin, cos) = 30f32.to_radians().sin_cos();
+
+    assert_angles(mounting.yaw_pitch_roll(), (30.0, 0.0, 0.0));
+    assert_vector(mounting.body(Vector::new(-sin, cos, 0.0)), (0.0, 1.0, 0.0));
+    assert!(close(mounting.heading_of(30.0), 0.0));
+    assert!(close(mounting.heading_of(10.0), 340.0));
+
+    let orientation = Orientation {
+        timestamp: 0,
+        yaw: 100.0,
+        pitch: 0.0,
+        roll: 0.0,
+        compass_heading: 130.0,
+        fused_heading: 130.0,
+    }
+    .to_robot_frame(&mounting);
+
+    assert!(close(orientation.yaw, 70.0));
+    assert!(close(orientation.compass_heading, 100.0));
+    assert!(close(orientation.fused_heading, 100.0));
+}
+
+/// With the board turned a quarter turn clockwise, the robot pitching up is a roll of the board.
+/// Converting back gives the pitch of the robot.
+#[test]
+fn turret_pitch_is_board_roll() {
+    let mounting = MountingRotation::from_yaw_pitch_roll(90.0, 0.0, 0.0);
+    let (yaw, pitch, roll) = board_angles(&mounting, (0.0, 10.0, 0.0));
+
+    assert_angles((yaw, pitch, roll), (90.0, 0.0, 10.0));
+    assert_angles(
+        mounting.yaw_pitch_roll_of(yaw, pitch, roll),
+        (0.0, 10.0, 0.0),
+    );
+}
+
+/// A board mounted upside down has its right and up axes reversed and still faces forward, so
+/// headings are unchanged.
+#[test]
+fn upside_down_mount() {
+    let mounting = MountingRotation::from_yaw_pitch_roll(0.0, 0.0, 180.0);
+
+    assert_vector(mounting.body(Vector::new(0.0, 0.0, -1.0)), (0.0, 0.0, 1.0));
+    assert_vector(mounting.body(Vector::new(1.0, 0.0, 0.0)), (-1.0, 0.0, 0.0));
+    assert_vector(mounting.body(Vector::new(0.0, 1.0, 0.0)), (0.0, 1.0, 0.0));
+    assert!(close(mounting.heading_of(45.0), 45.0));
+
+    let (yaw, pitch, roll) = board_angles(&mounting, (20.0, 10.0, -5.0));
+    assert_angles(
+        mounting.yaw_pitch_roll_of(yaw, pitch, roll),
+        (20.0, 10.0, -5.0),
+    );
+}
+
+/// Calibrating a turret mount that is slightly tilted recovers the tilt and keeps the yaw.
+#[test]
+fn level_calibration() {
+    let actual = MountingRotation::from_yaw_pitch_roll(30.0, 5.0, -3.0);
+    let accel = actual
+        .rotation()
+        .conjugate()
+        .rotate(Vector::new(0.0, 0.0, 1.0));
+
+    let mut mounting = MountingRotation::from_yaw_pitch_roll(30.0, 0.0, 0.0);
+    mounting.calibrate_level(accel);
+
+    assert_vector(mounting.body(accel), (0.0, 0.0, 1.0));
+    assert_angles(mounting.yaw_pitch_roll(), (30.0, 5.0, -3.0));
+}
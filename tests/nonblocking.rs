@@ -0,0 +1,169 @@
+//! Drives the non-blocking register transport and update stream against the in-memory fake board,
+//! polling by hand since no executor is a dependency.
+#![cfg(feature = "async")]
+
+use futures_core::Stream;
+use futures_io::{AsyncRead, AsyncWrite};
+use navx::nonblocking::fake::FakeTransport;
+use navx::nonblocking::{AsyncRegisterIO, AsyncRequestExt, UpdateStream};
+use navx::register::storage::Config;
+use navx::serial::packet::Update;
+use std::future::Future;
+use std::io::{self, ErrorKind};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+const DIRECTIONAL: &[u8] = b"!y 120.50  -3.25   7.00 270.1082\r\n";
+
+struct Noop;
+
+impl Wake for Noop {
+    fn wake(self: Arc<Self>) {}
+}
+
+/// Poll a future until it is ready, giving up if it makes no progress.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(Noop));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+
+    for _ in 0..100 {
+        if let Poll::Ready(x) = future.as_mut().poll(&mut cx) {
+            return x;
+        }
+    }
+    panic!("future never completed");
+}
+
+/// Poll a stream once
+fn poll_next<S: Stream + Unpin>(stream: &mut S) -> Poll<Option<S::Item>> {
+    let waker = Waker::from(Arc::new(Noop));
+    Pin::new(stream).poll_next(&mut Context::from_waker(&waker))
+}
+
+/// Poll a stream until it yields an item or ends.
+fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+    block_on(std::future::poll_fn(|cx| {
+        Pin::new(&mut *stream).poll_next(cx)
+    }))
+}
+
+#[test]
+fn register_read() {
+    let mut fake = FakeTransport::new();
+    fake.set_registers(0x04, &[50, 2, 0xD0, 0x07]);
+    let mut io = AsyncRegisterIO::new(fake);
+
+    let config: Config = block_on(io.read()).unwrap();
+    assert_eq!(config.update_rate, 50);
+    assert_eq!(config.accel_fsr, 2);
+    assert_eq!(config.gyro_fsr, 2000);
+    assert_eq!(io.written(), &[0x04, 4, 0x01]);
+
+    let stats = io.stats().snapshot();
+    assert_eq!(stats.transactions, 1);
+    assert_eq!(stats.bytes_sent, 3);
+    assert_eq!(stats.bytes_received, 5);
+}
+
+/// A response with the wrong checksum is rejected, and the next read starts over.
+#[test]
+fn register_crc_failure() {
+    let mut fake = FakeTransport::new();
+    fake.set_registers(0x04, &[50, 2, 0xD0, 0x07]);
+    fake.push_bytes(&[0, 0, 0, 0, 0xFF]);
+    let mut io = AsyncRegisterIO::new(fake);
+
+    let result: io::Result<Config> = block_on(io.read());
+    assert_eq!(result.err().unwrap().kind(), ErrorKind::InvalidData);
+    assert_eq!(io.stats().snapshot().crc_failures, 1);
+
+    let config: Config = block_on(io.read()).unwrap();
+    assert_eq!(config.update_rate, 50);
+}
+
+/// Accepts `accept` bytes of the request, then nothing more.
+struct Stalled {
+    accept: usize,
+}
+
+impl AsyncRead for Stalled {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        _: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(0))
+    }
+}
+
+impl AsyncWrite for Stalled {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let len = buf.len().min(self.accept);
+        self.accept -= len;
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// A write that accepts nothing fails, even after part of the request was sent.
+#[test]
+fn register_write_zero() {
+    for accept in 0..2 {
+        let mut io = AsyncRegisterIO::new(Stalled { accept });
+        let result: io::Result<Config> = block_on(io.read());
+
+        assert_eq!(result.err().unwrap().kind(), ErrorKind::WriteZero);
+    }
+}
+
+/// Hanging up in the middle of a response is an unexpected end of file.
+#[test]
+fn register_eof() {
+    let mut io = AsyncRegisterIO::new(Stalled { accept: 3 });
+    let result: io::Result<Config> = block_on(io.read());
+
+    assert_eq!(result.err().unwrap().kind(), ErrorKind::UnexpectedEof);
+}
+
+/// Packets are yielded as they complete, garbage between them is skipped, and the stream ends when
+/// the connection is closed.
+#[test]
+fn update_stream() {
+    let mut stream = UpdateStream::new(FakeTransport::new());
+
+    assert!(poll_next(&mut stream).is_pending());
+    stream.push_bytes(&DIRECTIONAL[..20]);
+    for _ in 0..10 {
+        assert!(poll_next(&mut stream).is_pending());
+    }
+
+    stream.push_bytes(&DIRECTIONAL[20..]);
+    stream.push_bytes(b"junk");
+    stream.push_bytes(DIRECTIONAL);
+    stream.close();
+
+    for _ in 0..2 {
+        match next(&mut stream) {
+            Some(Ok(sample)) => assert!(matches!(sample.value, Update::Directional(_))),
+            _ => panic!("expected a directional update"),
+        }
+    }
+    assert!(next(&mut stream).is_none());
+
+    let stats = stream.stats().snapshot();
+    assert_eq!(stats.samples, 2);
+    assert_eq!(stats.resyncs, 1);
+}
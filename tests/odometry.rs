@@ -0,0 +1,207 @@
+//! Drives synthetic trajectories through each drivetrain and checks that odometry ends up where the
+//! trajectory does. The navX angle is generated from the true heading, remembering that yaw
+//! increases clockwise while the pose heading increases counterclockwise.
+
+use navx::heading::ContinuousYaw;
+use navx::odometry::*;
+use std::f64::consts::PI;
+
+const STEPS: usize = 500;
+const EPSILON: f64 = 1e-6;
+
+/// The navX angle seen when the robot has a heading (degrees counterclockwise)
+fn yaw(heading: f64) -> ContinuousYaw {
+    ContinuousYaw::from_angle(-heading)
+}
+
+fn assert_pose(actual: Pose, expected: Pose) {
+    assert!(
+        (actual.x - expected.x).abs() < EPSILON
+            && (actual.y - expected.y).abs() < EPSILON
+            && (actual.heading - expected.heading).abs() < EPSILON,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn differential_straight_line() {
+    let mut odometry = Odometry::new(DifferentialDrive::new(0.6), yaw(0.0), Pose::default());
+    let deltas = DifferentialDeltas {
+        left: 0.01,
+        right: 0.01,
+    };
+
+    for _ in 0..STEPS {
+        odometry.update(yaw(0.0), &deltas);
+    }
+
+    assert_pose(odometry.pose(), Pose::new(5.0, 0.0, 0.0));
+}
+
+#[test]
+fn differential_semicircle() {
+    const RADIUS: f64 = 2.0;
+    const TRACK: f64 = 0.6;
+
+    let mut odometry = Odometry::new(DifferentialDrive::new(TRACK), yaw(0.0), Pose::default());
+    let step = PI / STEPS as f64;
+    let deltas = DifferentialDeltas {
+        left: (RADIUS - TRACK / 2.0) * step,
+        right: (RADIUS + TRACK / 2.0) * step,
+    };
+
+    for i in 1..=STEPS {
+        let twist = odometry.kinematics().twist(&deltas);
+        assert!((twist.dheading - step.to_degrees()).abs() < EPSILON);
+
+        odometry.update(yaw((i as f64 * step).to_degrees()), &deltas);
+    }
+
+    // A counterclockwise turn from the origin facing forward ends up to the left, facing back
+    assert_pose(odometry.pose(), Pose::new(0.0, 2.0 * RADIUS, 180.0));
+}
+
+#[test]
+fn heading_comes_from_navx() {
+    let mut odometry = Odometry::new(DifferentialDrive::new(0.6), yaw(0.0), Pose::default());
+
+    // The wheels slip as if turning, but the navX sees the robot driving straight
+    let deltas = DifferentialDeltas {
+        left: 0.005,
+        right: 0.015,
+    };
+
+    for _ in 0..STEPS {
+        odometry.update(yaw(0.0), &deltas);
+    }
+
+    assert_pose(odometry.pose(), Pose::new(5.0, 0.0, 0.0));
+}
+
+#[test]
+fn heading_is_continuous_past_wraparound() {
+    let mut odometry = Odometry::new(DifferentialDrive::new(0.6), yaw(170.0), Pose::default());
+    let deltas = DifferentialDeltas {
+        left: 0.0,
+        right: 0.0,
+    };
+
+    for i in 1..=20 {
+        let pose = odometry.update(yaw(170.0 + f64::from(i)), &deltas);
+        assert!((pose.heading - f64::from(i)).abs() < EPSILON);
+    }
+
+    // The navX reports the wrapped yaw, but the continuous angle keeps counting
+    assert!((yaw(190.0).yaw - 170.0).abs() < 1e-3);
+    assert_pose(odometry.pose(), Pose::new(0.0, 0.0, 20.0));
+}
+
+#[test]
+fn mecanum_strafe_and_drive() {
+    let mut odometry = Odometry::new(MecanumDrive::new(0.5, 0.6), yaw(0.0), Pose::default());
+    let strafe_left = MecanumDeltas {
+        front_left: -0.01,
+        front_right: 0.01,
+        rear_left: 0.01,
+        rear_right: -0.01,
+    };
+    let forward = MecanumDeltas {
+        front_left: 0.01,
+        front_right: 0.01,
+        rear_left: 0.01,
+        rear_right: 0.01,
+    };
+
+    for _ in 0..STEPS {
+        odometry.update(yaw(0.0), &strafe_left);
+    }
+    assert_pose(odometry.pose(), Pose::new(0.0, 5.0, 0.0));
+
+    for _ in 0..STEPS {
+        odometry.update(yaw(0.0), &forward);
+    }
+    assert_pose(odometry.pose(), Pose::new(5.0, 5.0, 0.0));
+}
+
+#[test]
+fn mecanum_rotation_matches_kinematics() {
+    let drive = MecanumDrive::new(0.5, 0.6);
+    let spin = MecanumDeltas {
+        front_left: -0.01,
+        front_right: 0.01,
+        rear_left: -0.01,
+        rear_right: 0.01,
+    };
+
+    let twist = drive.twist(&spin);
+    assert!(twist.dx.abs() < EPSILON && twist.dy.abs() < EPSILON);
+    assert!((twist.dheading - (0.01 / 0.55_f64).to_degrees()).abs() < EPSILON);
+}
+
+fn square_swerve() -> SwerveDrive {
+    SwerveDrive::new(&[(0.3, 0.3), (0.3, -0.3), (-0.3, 0.3), (-0.3, -0.3)])
+}
+
+#[test]
+fn swerve_spin_in_place() {
+    let mut odometry = Odometry::new(square_swerve(), yaw(0.0), Pose::default());
+    let step = (2.0 * PI) / STEPS as f64;
+
+    // Every module points along the circle through it and moves by the same arc length
+    let deltas: Vec<SwerveDelta> = odometry
+        .kinematics()
+        .modules()
+        .iter()
+        .map(|&(x, y)| SwerveDelta::new(x.hypot(y) * step, x.atan2(-y).to_degrees()))
+        .collect();
+
+    for i in 1..=STEPS {
+        let twist = odometry.kinematics().twist(&deltas);
+        assert!(twist.dx.abs() < EPSILON && twist.dy.abs() < EPSILON);
+        assert!((twist.dheading - step.to_degrees()).abs() < EPSILON);
+
+        odometry.update(yaw((i as f64 * step).to_degrees()), &deltas);
+    }
+
+    assert_pose(odometry.pose(), Pose::new(0.0, 0.0, 360.0));
+}
+
+#[test]
+fn swerve_translates_while_rotating() {
+    let mut odometry = Odometry::new(square_swerve(), yaw(0.0), Pose::default());
+    let step = 90.0 / STEPS as f64;
+
+    // Keep moving towards +X on the field while the robot turns a quarter turn. The modules steer
+    // against the rotation so the field relative direction stays the same.
+    for i in 0..STEPS {
+        let heading = (i as f64 + 0.5) * step;
+        let deltas = vec![SwerveDelta::new(0.01, -heading); 4];
+        odometry.update(yaw((i + 1) as f64 * step), &deltas);
+    }
+
+    let pose = odometry.pose();
+    assert!((pose.x - 5.0).abs() < 1e-4, "{:?}", pose);
+    assert!(pose.y.abs() < 1e-4, "{:?}", pose);
+    assert!((pose.heading - 90.0).abs() < EPSILON);
+}
+
+#[test]
+fn reset_moves_pose_and_keeps_tracking() {
+    let mut odometry = Odometry::new(DifferentialDrive::new(0.6), yaw(45.0), Pose::default());
+    assert_pose(odometry.pose(), Pose::default());
+
+    // The navX still reads 45 degrees, but the robot is placed facing +Y
+    odometry.reset(yaw(45.0), Pose::new(1.0, 2.0, 90.0));
+    let deltas = DifferentialDeltas {
+        left: 0.01,
+        right: 0.01,
+    };
+
+    for _ in 0..STEPS {
+        odometry.update(yaw(45.0), &deltas);
+    }
+
+    assert_pose(odometry.pose(), Pose::new(1.0, 7.0, 90.0));
+}
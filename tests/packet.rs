@@ -0,0 +1,163 @@
+//! Decodes serial packets built byte for byte from the navX protocol and checks which field each
+//! value lands in, along with how bad packets are skipped.
+
+use navx::serde::{CalibrationStatus, OperationStatus, SensorStatus, StreamType, Vector};
+use navx::serial::packet::{PacketReader, Update};
+use navx::Request;
+use std::io::{ErrorKind, Read};
+
+/// A heading update: yaw 120.5, roll -3.25, pitch 7, compass heading 270.1
+const DIRECTIONAL: &[u8] = b"!y 120.50  -3.25   7.00 270.1082\r\n";
+
+/// The checksum is the sum of every byte before it as two hex characters.
+fn checksum(packet: &[u8]) -> Vec<u8> {
+    let sum = packet.iter().fold(0u8, |sum, &x| sum.wrapping_add(x));
+    format!("{:02X}\r\n", sum).into_bytes()
+}
+
+fn ascii(msg_id: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![b'!', msg_id];
+    packet.extend_from_slice(body);
+    packet.extend(checksum(&packet));
+    packet
+}
+
+/// The length of a binary packet counts everything after the first two characters.
+fn binary(msg_id: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![b'!', b'#', (body.len() + 6) as u8, msg_id];
+    packet.extend_from_slice(body);
+    packet.extend(checksum(&packet));
+    packet
+}
+
+fn read_one(bytes: &[u8]) -> Update {
+    PacketReader::new(bytes).read().expect("no packet")
+}
+
+#[test]
+fn directional() {
+    assert_eq!(&ascii(b'y', &DIRECTIONAL[2..30])[..], DIRECTIONAL);
+
+    match read_one(DIRECTIONAL) {
+        Update::Directional(x) => {
+            assert_eq!(x.yaw, 120.5);
+            assert_eq!(x.roll, -3.25);
+            assert_eq!(x.pitch, 7.0);
+            assert_eq!(x.compass_heading, 270.1);
+        }
+        _ => panic!("expected a directional update"),
+    }
+}
+
+/// Every raw value is a signed short written as four hex characters, and the temperature is in
+/// hundredths of a degree.
+#[test]
+fn raw_data() {
+    let packet = ascii(b'g', b"0064FF9C00004000C0000001001AFFE60000FF38");
+
+    match read_one(&packet) {
+        Update::RawData(x) => {
+            assert_eq!(x.gyro, Vector::new(100, -100, 0));
+            assert_eq!(x.acceleration, Vector::new(16384, -16384, 1));
+            assert_eq!(x.magnetometer, Vector::new(26, -26, 0));
+            assert_eq!(x.temperature, -2.0);
+        }
+        _ => panic!("expected a raw data update"),
+    }
+}
+
+/// The flags are the last four hex characters, after the reserved bytes.
+#[test]
+fn stream_response() {
+    let body = [&b"p07D00002003C  12.50"[..], &[b'0'; 16], b"0006"].concat();
+    let packet = ascii(b's', &body);
+
+    match read_one(&packet) {
+        Update::StreamResponse(x) => {
+            assert!(matches!(x.stream_type, StreamType::Position));
+            assert_eq!(x.gyro_fsr, 2000);
+            assert_eq!(x.accel_fsr, 2);
+            assert_eq!(x.update_rate, 60);
+            assert_eq!(x.calibrated_yaw_offset, 12.5);
+            assert_eq!(&x.reserved, b"0000000000000000");
+            assert_eq!(
+                x.flags,
+                CalibrationStatus::IMU_COMPLETE | CalibrationStatus::MAG_COMPLETE
+            );
+        }
+        _ => panic!("expected a stream response"),
+    }
+}
+
+/// A position update is little endian binary, with roll before pitch.
+#[test]
+fn position() {
+    let mut body = Vec::new();
+    for x in &[-4500i16, 300, -150] {
+        body.extend_from_slice(&x.to_le_bytes());
+    }
+    body.extend_from_slice(&9000u16.to_le_bytes()); // Compass heading
+    body.extend_from_slice(&[0; 4]); // Altitude
+    body.extend_from_slice(&31500u16.to_le_bytes()); // Fused heading
+    for x in &[250i16, -500, 1000] {
+        body.extend_from_slice(&x.to_le_bytes());
+    }
+    body.extend_from_slice(&[0; 24]); // Velocity and displacement
+    for x in &[16384i16, 0, 0, 0] {
+        body.extend_from_slice(&x.to_le_bytes());
+    }
+    body.extend_from_slice(&3150i16.to_le_bytes());
+    body.extend_from_slice(&[4, 0x02, 0x02, 0x80]);
+    assert_eq!(body.len(), 58);
+
+    match read_one(&binary(b'p', &body)) {
+        Update::Position(x) => {
+            assert_eq!(x.yaw, -45.0);
+            assert_eq!(x.roll, 3.0);
+            assert_eq!(x.pitch, -1.5);
+            assert_eq!(x.compass_heading, 90.0);
+            assert_eq!(x.fused_heading, 315.0);
+            assert_eq!(x.linear_accel, Vector::new(0.25, -0.5, 1.0));
+            assert_eq!(x.quaternion.w, 1.0);
+            assert_eq!(x.mpu_temp, 31.5);
+            assert!(matches!(x.status.operation, OperationStatus::Normal));
+            assert_eq!(x.status.sensor, SensorStatus::YAW_STABLE);
+        }
+        _ => panic!("expected a position update"),
+    }
+}
+
+/// Garbage and a packet with a bad checksum are skipped as a single resync.
+#[test]
+fn skips_bad_packets() {
+    let mut bytes = b"junk".to_vec();
+    let mut corrupt = DIRECTIONAL.to_vec();
+    corrupt[5] = b'9';
+    bytes.extend(corrupt);
+    bytes.extend_from_slice(DIRECTIONAL);
+
+    let mut reader = PacketReader::new(&bytes[..]);
+    assert!(matches!(reader.read(), Ok(Update::Directional(_))));
+
+    let stats = reader.stats().snapshot();
+    assert_eq!(stats.transactions, 1);
+    assert_eq!(stats.crc_failures, 1);
+    assert_eq!(stats.resyncs, 1);
+}
+
+/// A packet split across reads is only returned once it is complete.
+#[test]
+fn split_packet() {
+    let (first, second) = DIRECTIONAL.split_at(20);
+    let mut reader = PacketReader::new(first.chain(second));
+
+    assert!(matches!(reader.read(), Ok(Update::Directional(_))));
+}
+
+/// Running out of bytes is reported as a timeout.
+#[test]
+fn no_bytes() {
+    let mut reader = PacketReader::new(&DIRECTIONAL[..20]);
+
+    assert_eq!(reader.read().err().unwrap().kind(), ErrorKind::Interrupted);
+}
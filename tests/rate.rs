@@ -0,0 +1,72 @@
+//! Checks that rates read from the gyro and rates differentiated from yaw agree on the sign of a
+//! turn. The board yaw increases clockwise, while the gyro measures right handed rates.
+
+use navx::math::Quaternion;
+use navx::rate::{AngularRate, YawDifferentiator};
+use navx::serde::Vector;
+use navx::watch::Sample;
+use std::time::Instant;
+
+const EPSILON: f32 = 1e-3;
+
+fn sample(yaw: f32, timestamp: u32) -> Sample<f32> {
+    Sample {
+        host_time: Instant::now(),
+        board_timestamp: Some(timestamp),
+        value: yaw,
+    }
+}
+
+/// Turning clockwise at 90 degrees/sec reads -90 about the up axis of a level board.
+#[test]
+fn clockwise_turn_is_positive() {
+    let rate = AngularRate::new(Vector::new(0.0, 0.0, -90.0), &Quaternion::IDENTITY);
+    assert!((rate.yaw_rate - 90.0).abs() < EPSILON);
+}
+
+/// A full scale raw reading is the gyro full scale range.
+#[test]
+fn raw_rate() {
+    let rate = AngularRate::from_raw(Vector::new(0, 0, -16384), 2000, &Quaternion::IDENTITY);
+    assert!((rate.body.z + 1000.0).abs() < EPSILON);
+    assert!((rate.yaw_rate - 1000.0).abs() < EPSILON);
+}
+
+/// A board rolled 90 degrees measures the turn on a different axis, but the world yaw rate stays
+/// the same.
+#[test]
+fn tilted_board() {
+    let orientation = Quaternion::from_yaw_pitch_roll(0.0, 0.0, 90.0);
+    let body = orientation.conjugate().rotate(Vector::new(0.0, 0.0, -90.0));
+    let rate = AngularRate::new(body, &orientation);
+
+    assert!(body.z.abs() < EPSILON);
+    assert!((rate.yaw_rate - 90.0).abs() < EPSILON);
+}
+
+/// Differentiating the yaw of the same clockwise turn gives the same rate as the gyro.
+#[test]
+fn matches_differentiator() {
+    let gyro = AngularRate::from_raw(Vector::new(0, 0, -1638), 2000, &Quaternion::IDENTITY);
+    let mut differentiator = YawDifferentiator::default();
+
+    differentiator.update(&sample(170.0, 0));
+    let derived = differentiator.update(&sample(-170.0, 200)).unwrap();
+
+    assert!((derived - 100.0).abs() < EPSILON);
+    assert!((gyro.yaw_rate - derived).abs() < 0.1);
+    assert_eq!(gyro.yaw_rate.signum(), derived.signum());
+}
+
+/// The board timestamp wrapping around is a short step forward, while a step back is ignored.
+#[test]
+fn timestamp_wraps() {
+    let mut differentiator = YawDifferentiator::default();
+
+    differentiator.update(&sample(170.0, u32::max_value() - 99));
+    let rate = differentiator.update(&sample(-170.0, 100)).unwrap();
+    assert!((rate - 100.0).abs() < EPSILON);
+
+    let rate = differentiator.update(&sample(0.0, 50)).unwrap();
+    assert!((rate - 100.0).abs() < EPSILON);
+}
@@ -0,0 +1,171 @@
+//! Runs watchers on a provider that fails a set number of times before succeeding, and checks how
+//! they back off, recover the connection and give up.
+
+use navx::watch::{Builder, RetryPolicy};
+use navx::Request;
+use parking_lot::Mutex;
+use std::io::{self, ErrorKind};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use Call::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Call {
+    Read,
+    Recover,
+}
+
+/// Fails the first `failures` reads with a timeout, then reads 1 forever.
+struct Flaky {
+    failures: usize,
+    calls: Arc<Mutex<Vec<(Call, Instant)>>>,
+}
+
+impl Flaky {
+    fn new(failures: usize) -> (Self, Arc<Mutex<Vec<(Call, Instant)>>>) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let flaky = Self {
+            failures,
+            calls: calls.clone(),
+        };
+        (flaky, calls)
+    }
+}
+
+impl Request<u32> for Flaky {
+    fn read(&mut self) -> io::Result<u32> {
+        self.calls.lock().push((Call::Read, Instant::now()));
+
+        if self.failures > 0 {
+            self.failures -= 1;
+            return Err(io::Error::new(ErrorKind::TimedOut, "no response"));
+        }
+        Ok(1)
+    }
+
+    fn recover(&mut self) -> io::Result<()> {
+        self.calls.lock().push((Call::Recover, Instant::now()));
+        Ok(())
+    }
+}
+
+fn policy(max_failures: usize, initial_backoff: Duration) -> RetryPolicy {
+    RetryPolicy {
+        max_failures,
+        initial_backoff,
+        max_backoff: Duration::from_secs(1),
+        multiplier: 2,
+    }
+}
+
+/// Wait for a condition, failing the test if it takes too long.
+fn wait_for<F: Fn() -> bool>(condition: F) {
+    let start = Instant::now();
+
+    while !condition() {
+        assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// The backoff doubles with every consecutive failure up to the limit.
+#[test]
+fn exponential_backoff() {
+    let retry = RetryPolicy::default();
+    let expected = [10, 10, 20, 40, 80, 160, 320, 640, 1000, 1000];
+
+    for (failures, &ms) in expected.iter().enumerate() {
+        assert_eq!(retry.backoff(failures), Duration::from_millis(ms));
+    }
+    assert_eq!(retry.backoff(1000), Duration::from_secs(1));
+
+    let retry = RetryPolicy {
+        multiplier: 3,
+        ..retry
+    };
+    assert_eq!(retry.backoff(3), Duration::from_millis(90));
+}
+
+/// Every retry recovers the connection first, after waiting out the backoff.
+#[test]
+fn recovers_before_retrying() {
+    let (flaky, calls) = Flaky::new(3);
+    let retry = policy(5, Duration::from_millis(10));
+    let watched = Builder::new().rate(1000).retry(retry).watch(flaky);
+
+    wait_for(|| watched.is_ready());
+    assert_eq!(watched.get().unwrap(), 1);
+
+    let calls = calls.lock().clone();
+    let kinds: Vec<_> = calls.iter().take(7).map(|x| x.0).collect();
+    assert_eq!(
+        kinds,
+        vec![Read, Recover, Read, Recover, Read, Recover, Read]
+    );
+
+    for failures in 1..=3 {
+        let gap = calls[failures * 2 - 1].1 - calls[failures * 2 - 2].1;
+        assert!(
+            gap >= retry.backoff(failures),
+            "waited {:?} after {} failures",
+            gap,
+            failures
+        );
+    }
+
+    // Reads go back to normal once one succeeds
+    assert!(calls[7..].iter().all(|x| x.0 == Read));
+}
+
+/// The errors can be checked while the watcher is still retrying, and the consecutive count resets
+/// once a read succeeds.
+#[test]
+fn errors_while_running() {
+    let (flaky, _) = Flaky::new(3);
+    let retry = policy(5, Duration::from_millis(50));
+    let watched = Builder::new().rate(1000).retry(retry).watch(flaky);
+
+    wait_for(|| watched.errors().total == 2);
+    let errors = watched.errors();
+    assert_eq!(errors.consecutive, 2);
+    assert_eq!(errors.last.unwrap().kind(), ErrorKind::TimedOut);
+    assert!(!watched.is_stopped());
+    assert!(!watched.is_ready());
+
+    wait_for(|| watched.is_ready());
+    let errors = watched.errors();
+    assert_eq!(errors.consecutive, 0);
+    assert_eq!(errors.total, 3);
+    assert!(errors.last.is_some());
+}
+
+/// The watcher gives up once there are more consecutive failures than allowed, and keeps the last
+/// error.
+#[test]
+fn gives_up_after_max_failures() {
+    let (flaky, calls) = Flaky::new(usize::max_value());
+    let retry = policy(2, Duration::from_millis(1));
+    let watched = Builder::new().rate(1000).retry(retry).watch(flaky);
+
+    wait_for(|| watched.is_stopped());
+    assert_eq!(watched.errors().total, 3);
+    assert_eq!(watched.get().unwrap_err().kind(), ErrorKind::TimedOut);
+    assert_eq!(calls.lock().iter().filter(|x| x.0 == Read).count(), 3);
+    assert_eq!(watched.stop().err().unwrap().kind(), ErrorKind::TimedOut);
+}
+
+/// A watcher that never retries stops on the first error without recovering.
+#[test]
+fn never_retry() {
+    let (flaky, calls) = Flaky::new(1);
+    let watched = Builder::new()
+        .rate(1000)
+        .retry(RetryPolicy::never())
+        .watch(flaky);
+
+    wait_for(|| watched.is_stopped());
+    assert_eq!(watched.errors().total, 1);
+    assert_eq!(calls.lock().len(), 1);
+}
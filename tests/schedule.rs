@@ -0,0 +1,147 @@
+//! Polls several values from one fake transport and checks that each is read at its own rate, that
+//! one time values are read once and that a failing value does not stop the others.
+
+use navx::schedule::{Rate, Scheduler};
+use navx::subscribe::Capacity;
+use navx::Request;
+use std::io::{self, ErrorKind};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Fast(usize);
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Slow(usize);
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Identity;
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Broken;
+
+/// Counts the reads of every value. The identity is only available after a few attempts, and the
+/// broken value fails after a few reads.
+#[derive(Default)]
+struct Transport {
+    fast: usize,
+    slow: usize,
+    identity: usize,
+    broken: usize,
+}
+
+impl Request<Fast> for Transport {
+    fn read(&mut self) -> io::Result<Fast> {
+        self.fast += 1;
+        Ok(Fast(self.fast))
+    }
+}
+
+impl Request<Slow> for Transport {
+    fn read(&mut self) -> io::Result<Slow> {
+        self.slow += 1;
+        Ok(Slow(self.slow))
+    }
+}
+
+impl Request<Identity> for Transport {
+    fn read(&mut self) -> io::Result<Identity> {
+        self.identity += 1;
+
+        if self.identity < 3 {
+            return Err(ErrorKind::Interrupted.into());
+        }
+        Ok(Identity)
+    }
+}
+
+impl Request<Broken> for Transport {
+    fn read(&mut self) -> io::Result<Broken> {
+        self.broken += 1;
+
+        if self.broken > 2 {
+            return Err(io::Error::new(ErrorKind::InvalidData, "bad value"));
+        }
+        Ok(Broken)
+    }
+}
+
+/// Wait for a condition, failing the test if it takes too long.
+fn wait_for<F: Fn() -> bool>(condition: F) {
+    let start = Instant::now();
+
+    while !condition() {
+        assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Each value is read at its own rate on the shared transport.
+#[test]
+fn rates() {
+    let mut scheduler = Scheduler::new(Transport::default());
+    let fast = scheduler.add::<Fast>(Rate::Hz(200));
+    let slow = scheduler.add::<Slow>(Rate::Period(Duration::from_millis(50)));
+    let subscription = fast.subscribe(Capacity::Unbounded);
+
+    let scheduled = scheduler.start();
+    thread::sleep(Duration::from_millis(500));
+    let transport = scheduled.stop().unwrap();
+
+    // 100 and 10 reads are expected, with room for a slow test machine
+    assert!((50..=102).contains(&transport.fast), "{}", transport.fast);
+    assert!((5..=12).contains(&transport.slow), "{}", transport.slow);
+    assert!(transport.fast >= transport.slow * 5);
+
+    assert_eq!(fast.get().unwrap(), Fast(transport.fast));
+    assert_eq!(slow.get().unwrap(), Slow(transport.slow));
+    assert_eq!(fast.version(), transport.fast);
+
+    let values: Vec<_> = subscription.try_iter().map(|x| x.value.0).collect();
+    assert_eq!(values, (1..=transport.fast).collect::<Vec<_>>());
+}
+
+/// A one time value is retried until it is read, and then never read again.
+#[test]
+fn once() {
+    let mut scheduler = Scheduler::new(Transport::default());
+    let identity = scheduler.add::<Identity>(Rate::Once);
+    assert!(!identity.is_ready());
+
+    let scheduled = scheduler.start();
+    wait_for(|| identity.is_ready());
+    thread::sleep(Duration::from_millis(150));
+    let transport = scheduled.stop().unwrap();
+
+    assert_eq!(identity.get().unwrap(), Identity);
+    assert_eq!(identity.version(), 1);
+    assert_eq!(transport.identity, 3);
+}
+
+/// A value that fails keeps the error and stops being read, while the others keep going.
+#[test]
+fn failure_stops_one_value() {
+    let mut scheduler = Scheduler::new(Transport::default());
+    let broken = scheduler.add::<Broken>(Rate::Hz(500));
+    let fast = scheduler.add::<Fast>(Rate::Hz(500));
+
+    let scheduled = scheduler.start();
+    wait_for(|| broken.failure().is_some());
+    let version = fast.version();
+    wait_for(|| fast.version() > version + 10);
+    let transport = scheduled.stop().unwrap();
+
+    assert_eq!(transport.broken, 3);
+    assert_eq!(broken.version(), 2);
+    assert_eq!(broken.get().unwrap_err().kind(), ErrorKind::InvalidData);
+    assert!(fast.failure().is_none());
+}
+
+/// The scheduler stops when the handle is dropped, even with nothing to poll.
+#[test]
+fn stop_when_idle() {
+    let scheduled = Scheduler::new(Transport::default()).start();
+    assert!(!scheduled.is_stopped());
+
+    let start = Instant::now();
+    let transport = scheduled.stop().unwrap();
+    assert!(start.elapsed() < Duration::from_millis(50));
+    assert_eq!(transport.fast, 0);
+}
@@ -0,0 +1,136 @@
+//! Records known latencies and sample times into the link statistics and checks the computed
+//! summaries.
+
+use navx::stats::{LinkStats, STATS_WINDOW};
+use std::time::{Duration, Instant};
+
+const EPSILON: f64 = 1e-6;
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+/// Record a sample at each of the given times in milliseconds.
+fn samples(stats: &LinkStats, times: &[u64]) {
+    let start = Instant::now();
+
+    for &time in times {
+        stats.record_sample(start + ms(time));
+    }
+}
+
+#[test]
+fn empty() {
+    let snapshot = LinkStats::new().snapshot();
+
+    assert_eq!(snapshot.transactions, 0);
+    assert_eq!(snapshot.latency, None);
+    assert_eq!(snapshot.sample_rate, None);
+    assert_eq!(snapshot.jitter, None);
+}
+
+/// Clones share the same counters.
+#[test]
+fn counters() {
+    let stats = LinkStats::new();
+    let clone = stats.clone();
+
+    stats.record_transaction();
+    clone.record_transaction();
+    stats.record_sent(3);
+    clone.record_received(5);
+    stats.record_crc_failure();
+    stats.record_short_read();
+    stats.record_decode_failure();
+    stats.record_retry();
+    stats.record_resync();
+
+    let snapshot = clone.snapshot();
+    assert_eq!(snapshot.transactions, 2);
+    assert_eq!(snapshot.bytes_sent, 3);
+    assert_eq!(snapshot.bytes_received, 5);
+    assert_eq!(snapshot.crc_failures, 1);
+    assert_eq!(snapshot.short_reads, 1);
+    assert_eq!(snapshot.decode_failures, 1);
+    assert_eq!(snapshot.retries, 1);
+    assert_eq!(snapshot.resyncs, 1);
+
+    stats.reset();
+    assert_eq!(clone.snapshot(), LinkStats::new().snapshot());
+}
+
+/// With latencies of 1 to 200ms, 99% of transactions took at most 199ms.
+#[test]
+fn latency() {
+    let stats = LinkStats::new();
+    for i in (1..=200).rev() {
+        stats.record_latency(ms(i));
+    }
+
+    let latency = stats.snapshot().latency.unwrap();
+    assert_eq!(latency.min, ms(1));
+    assert_eq!(latency.mean, Duration::from_micros(100_500));
+    assert_eq!(latency.max, ms(200));
+    assert_eq!(latency.p99, ms(199));
+
+    let stats = LinkStats::new();
+    stats.record_latency(ms(7));
+    let latency = stats.snapshot().latency.unwrap();
+    assert_eq!(
+        (latency.min, latency.max, latency.p99),
+        (ms(7), ms(7), ms(7))
+    );
+}
+
+/// Only the most recent latencies count.
+#[test]
+fn latency_window() {
+    let stats = LinkStats::new();
+    for i in 1..=(STATS_WINDOW as u64 + 500) {
+        stats.record_latency(ms(i));
+    }
+
+    let latency = stats.snapshot().latency.unwrap();
+    assert_eq!(latency.min, ms(501));
+    assert_eq!(latency.max, ms(STATS_WINDOW as u64 + 500));
+}
+
+/// Evenly spaced samples have no jitter.
+#[test]
+fn steady_rate() {
+    let stats = LinkStats::new();
+    let times: Vec<_> = (0..=50).map(|i| i * 10).collect();
+    samples(&stats, &times);
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.samples, 51);
+    assert!((snapshot.sample_rate.unwrap() - 100.0).abs() < EPSILON);
+    assert_eq!(snapshot.jitter, Some(Duration::from_secs(0)));
+}
+
+/// Intervals alternating between 10 and 30ms average 50Hz and deviate from their mean by 10ms.
+#[test]
+fn jitter() {
+    let stats = LinkStats::new();
+    samples(&stats, &[0, 10, 40, 50, 80, 90, 120]);
+
+    let snapshot = stats.snapshot();
+    assert!((snapshot.sample_rate.unwrap() - 50.0).abs() < EPSILON);
+
+    let jitter = snapshot.jitter.unwrap().as_secs_f64();
+    assert!((jitter - 0.01).abs() < EPSILON, "{}", jitter);
+}
+
+/// A rate needs two samples, and jitter needs two intervals.
+#[test]
+fn too_few_samples() {
+    let stats = LinkStats::new();
+    samples(&stats, &[0]);
+    assert_eq!(stats.snapshot().sample_rate, None);
+
+    let stats = LinkStats::new();
+    samples(&stats, &[0, 20]);
+    let snapshot = stats.snapshot();
+    assert!((snapshot.sample_rate.unwrap() - 50.0).abs() < EPSILON);
+    assert_eq!(snapshot.jitter, None);
+}
@@ -0,0 +1,118 @@
+//! Checks the register block of each value against the navX register map, so values are never
+//! decoded from the neighbouring registers.
+
+use navx::register::storage::*;
+use navx::serde::*;
+use navx::{FromBuffer, FromBufferFallible};
+
+#[test]
+fn identity_address() {
+    assert_eq!(Identity::ADDRESS, 0x00);
+    assert_eq!(Identity::LEN, 4);
+}
+
+#[test]
+fn config_address() {
+    assert_eq!(Config::ADDRESS, 0x04);
+    assert_eq!(Config::LEN, 4);
+}
+
+#[test]
+fn orientation_address() {
+    assert_eq!(Orientation::ADDRESS, 0x12);
+    assert_eq!(Orientation::LEN, 14);
+}
+
+/// World frame linear acceleration starts at `NAVX_REG_LINEAR_ACC_X_L`, right after the altitude.
+#[test]
+fn linear_accel_address() {
+    assert_eq!(LinearAccel::ADDRESS, 0x24);
+    assert_eq!(LinearAccel::LEN, 6);
+}
+
+#[test]
+fn quaternion_address() {
+    assert_eq!(Quaternion::ADDRESS, 0x2A);
+    assert_eq!(Quaternion::LEN, 8);
+}
+
+#[test]
+fn raw_gyro_address() {
+    assert_eq!(RawGyro::ADDRESS, 0x34);
+    assert_eq!(RawGyro::LEN, 6);
+}
+
+/// The linear acceleration block ends where the quaternion begins.
+#[test]
+fn linear_accel_is_before_quaternion() {
+    assert_eq!(
+        LinearAccel::ADDRESS as usize + LinearAccel::LEN,
+        Quaternion::ADDRESS as usize
+    );
+}
+
+/// Each axis is a signed value in thousandths of a G.
+#[test]
+fn linear_accel_decode() {
+    let accel = LinearAccel::read(&[0xE8, 0x03, 0x18, 0xFC, 0xF4, 0x01]).accel;
+    assert_eq!((accel.x, accel.y, accel.z), (1.0, -1.0, 0.5));
+}
+
+/// The register values have the same unit-typed accessors as the serial updates.
+#[cfg(feature = "units")]
+#[test]
+fn register_units() {
+    use navx::units::*;
+
+    let accel = LinearAccel::read(&[0xE8, 0x03, 0x18, 0xFC, 0xF4, 0x01]).accel();
+    let accel = MetersPerSecondSquared::from(accel.y);
+    assert!((accel.0 + STANDARD_GRAVITY).abs() < 1e-6);
+
+    let gyro = RawGyro::read(&[0x00, 0x40, 0x00, 0xC0, 0x00, 0x00]);
+    let gyro = gyro.gyro(DegreesPerSecond(2000.0));
+    assert_eq!((gyro.x.0, gyro.y.0, gyro.z.0), (1000.0, -1000.0, 0.0));
+
+    let mut buf = [0; 14];
+    buf[4..6].copy_from_slice(&(-9000i16).to_le_bytes());
+    buf[12..14].copy_from_slice(&27000u16.to_le_bytes());
+    let orientation = Orientation::read(&buf);
+    assert_eq!(orientation.yaw(), Degrees(-90.0));
+    assert_eq!(orientation.fused_heading(), Degrees(270.0));
+    assert!((Radians::from(orientation.yaw()).0 + std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+}
+
+/// The status block starts at the operation status and ends with the low byte of the sensor
+/// status at `NAVX_REG_SENSOR_STATUS_L`, right before the orientation.
+#[test]
+fn status_address() {
+    assert_eq!(Status::ADDRESS, 0x08);
+    assert_eq!(Status::ADDRESS as usize + Status::LEN, 0x11);
+}
+
+/// The sensor flags come from 0x10, not from the high capability byte or the unused registers.
+#[test]
+fn status_decode() {
+    let buf = [4, 0x02, 0x81, 0x44, 0xFF, 0xFF, 0xFF, 0xFF, 0x23];
+    let status = Status::try_read(&buf).unwrap();
+
+    assert!(matches!(status.operation_status, OperationStatus::Normal));
+    assert_eq!(status.calibration_status, CalibrationStatus::IMU_COMPLETE);
+    assert_eq!(
+        status.self_test_status,
+        SelfTestStatus::GYRO_PASSED | SelfTestStatus::COMPLETE
+    );
+    assert_eq!(
+        status.capabilities,
+        Capability::OMNIMOUNT | Capability::VEL_AND_DISP
+    );
+    assert_eq!(
+        status.sensor_status,
+        SensorStatus::MOVING | SensorStatus::YAW_STABLE | SensorStatus::FUSED_HEADING_VALID
+    );
+}
+
+/// An unknown operation status is rejected.
+#[test]
+fn status_invalid() {
+    assert!(Status::try_read(&[5, 0, 0, 0, 0, 0, 0, 0, 0]).is_none());
+}
@@ -0,0 +1,141 @@
+//! Publishes numbered samples to subscribers with each overflow policy and checks which ones are
+//! received and how many are counted as dropped.
+
+use navx::subscribe::{Capacity, Overflow, Subscribers, Subscription};
+use navx::watch::{Builder, Sample};
+use navx::Request;
+use std::io;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn publish(subscribers: &Subscribers<u32>, values: std::ops::RangeInclusive<u32>) {
+    for value in values {
+        subscribers.publish(&Sample {
+            host_time: Instant::now(),
+            board_timestamp: None,
+            value,
+        });
+    }
+}
+
+fn received(subscription: &Subscription<u32>) -> Vec<u32> {
+    subscription.try_iter().map(|x| x.value).collect()
+}
+
+#[test]
+fn unbounded() {
+    let subscribers = Subscribers::new();
+    let subscription = subscribers.subscribe(Capacity::Unbounded);
+    publish(&subscribers, 1..=100);
+
+    assert_eq!(received(&subscription), (1..=100).collect::<Vec<_>>());
+    assert_eq!(subscription.dropped(), 0);
+}
+
+/// A full buffer keeps the samples it has and counts the new ones as dropped.
+#[test]
+fn drop_newest() {
+    let subscribers = Subscribers::new();
+    let subscription = subscribers.subscribe(Capacity::Bounded(2, Overflow::DropNewest));
+    publish(&subscribers, 1..=5);
+
+    assert_eq!(received(&subscription), vec![1, 2]);
+    assert_eq!(subscription.dropped(), 3);
+}
+
+/// A full buffer discards its oldest samples to make room, so the newest ones are received.
+#[test]
+fn drop_oldest() {
+    let subscribers = Subscribers::new();
+    let subscription = subscribers.subscribe(Capacity::Bounded(2, Overflow::DropOldest));
+    publish(&subscribers, 1..=5);
+
+    assert_eq!(received(&subscription), vec![4, 5]);
+    assert_eq!(subscription.dropped(), 3);
+}
+
+/// A capacity of zero buffers a single sample instead of never accepting one.
+#[test]
+fn zero_capacity() {
+    let subscribers = Subscribers::new();
+    let oldest = subscribers.subscribe(Capacity::Bounded(0, Overflow::DropOldest));
+    let newest = subscribers.subscribe(Capacity::Bounded(0, Overflow::DropNewest));
+    publish(&subscribers, 1..=3);
+
+    assert_eq!(received(&oldest), vec![3]);
+    assert_eq!(oldest.dropped(), 2);
+    assert_eq!(received(&newest), vec![1]);
+    assert_eq!(newest.dropped(), 2);
+}
+
+/// Subscriptions that have been dropped stop being published to.
+#[test]
+fn dropped_subscriptions_are_removed() {
+    let subscribers = Subscribers::new();
+
+    for overflow in &[Overflow::DropOldest, Overflow::DropNewest, Overflow::Block] {
+        drop(subscribers.subscribe(Capacity::Bounded(1, *overflow)));
+    }
+    drop(subscribers.subscribe(Capacity::Unbounded));
+    publish(&subscribers, 1..=2);
+
+    assert!(subscribers.is_empty());
+}
+
+/// Publishing waits for a full subscriber to make room, and nothing is dropped.
+#[test]
+fn block() {
+    let subscribers = Arc::new(Subscribers::new());
+    let subscription = subscribers.subscribe(Capacity::Bounded(1, Overflow::Block));
+
+    let publisher = subscribers.clone();
+    let handle = thread::spawn(move || publish(&publisher, 1..=3));
+
+    thread::sleep(Duration::from_millis(50));
+    assert!(!handle.is_finished());
+    assert_eq!(subscription.len(), 1);
+
+    let values: Vec<_> = (0..3).map(|_| subscription.recv().unwrap().value).collect();
+    handle.join().unwrap();
+
+    assert_eq!(values, vec![1, 2, 3]);
+    assert_eq!(subscription.dropped(), 0);
+}
+
+/// Closing the subscribers releases a publisher blocked on a subscriber that stopped receiving.
+#[test]
+fn block_until_closed() {
+    let subscribers = Arc::new(Subscribers::new());
+    let subscription = subscribers.subscribe(Capacity::Bounded(1, Overflow::Block));
+
+    let publisher = subscribers.clone();
+    let handle = thread::spawn(move || publish(&publisher, 1..=3));
+
+    thread::sleep(Duration::from_millis(50));
+    subscribers.close();
+    handle.join().unwrap();
+
+    assert_eq!(received(&subscription), vec![1]);
+}
+
+struct Counter(u32);
+
+impl Request<u32> for Counter {
+    fn read(&mut self) -> io::Result<u32> {
+        self.0 += 1;
+        Ok(self.0)
+    }
+}
+
+/// Stopping a watcher does not wait forever on a blocking subscriber that is never read.
+#[test]
+fn watcher_stops_with_blocked_subscriber() {
+    let watched = Builder::new().rate(1000).watch(Counter(0));
+    let subscription = watched.subscribe(Capacity::Bounded(1, Overflow::Block));
+
+    thread::sleep(Duration::from_millis(50));
+    drop(watched);
+
+    assert_eq!(received(&subscription).len(), 1);
+}
@@ -0,0 +1,47 @@
+//! Checks that thread options which can't be applied are caught instead of stopping the thread.
+
+use navx::thread::ThreadConfig;
+use navx::watch::Builder;
+use navx::Request;
+use std::io::{self, ErrorKind};
+use std::thread;
+use std::time::{Duration, Instant};
+
+struct Counter(u32);
+
+impl Request<u32> for Counter {
+    fn read(&mut self) -> io::Result<u32> {
+        self.0 += 1;
+        Ok(self.0)
+    }
+}
+
+#[test]
+fn validate() {
+    assert!(ThreadConfig::new().validate().is_ok());
+    assert!(ThreadConfig::new().cpu_affinity(&[0]).validate().is_ok());
+
+    let err = ThreadConfig::new()
+        .cpu_affinity(&[0, usize::MAX])
+        .validate()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+/// A CPU past the end of the CPU set is left out and the watcher keeps polling.
+#[test]
+fn invalid_cpu_affinity() {
+    let watched = Builder::new()
+        .rate(1000)
+        .cpu_affinity(&[usize::MAX])
+        .watch(Counter(0));
+
+    let start = Instant::now();
+    while !watched.is_ready() {
+        assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    assert!(watched.get().unwrap() > 0);
+    assert!(!watched.is_stopped());
+}
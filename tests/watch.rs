@@ -0,0 +1,52 @@
+//! Checks that watchers poll at the update rate of the board, even through wrapping providers.
+
+use navx::convention::{Converted, CoordinateConvention};
+use navx::mount::{Mounted, MountingRotation};
+use navx::register::storage::LinearAccel;
+use navx::serde::Vector;
+use navx::watch::{Builder, Watcher};
+use navx::Request;
+use std::io;
+use std::time::Duration;
+
+/// A provider for a board configured to update at the given rate
+struct Board(Option<u32>);
+
+impl Request<LinearAccel> for Board {
+    fn read(&mut self) -> io::Result<LinearAccel> {
+        Ok(LinearAccel {
+            accel: Vector::new(0.0, 0.0, 1.0),
+        })
+    }
+
+    fn update_rate(&mut self) -> Option<u32> {
+        self.0
+    }
+}
+
+/// Without a known rate the navX default of 50Hz is used.
+#[test]
+fn default_rate() {
+    let watcher = Watcher::new(Board(None));
+    assert_eq!(watcher.period(), Duration::from_millis(20));
+}
+
+/// The rate of the board is used directly and through every wrapper.
+#[test]
+fn board_rate() {
+    assert_eq!(
+        Watcher::new(Board(Some(100))).period(),
+        Duration::from_millis(10)
+    );
+
+    let converted = Converted::new(Board(Some(100)), CoordinateConvention::Nwu);
+    let mounted = Mounted::new(converted, MountingRotation::default());
+    assert_eq!(Watcher::new(mounted).period(), Duration::from_millis(10));
+}
+
+/// A rate given to the builder takes precedence over the board.
+#[test]
+fn builder_rate() {
+    let watcher = Watcher::with_builder(Board(Some(100)), Builder::new().rate(200));
+    assert_eq!(watcher.period(), Duration::from_millis(5));
+}